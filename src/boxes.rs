@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use log::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::csv_logic::{game_data, BoxRow, Cards};
+use crate::data_ref::*;
+use crate::experience::BattleResult;
+use crate::packets::commands::{DeliveryItem, ITEM_BRAWLER, ITEM_GOLD, ITEM_POWER_POINTS};
+use crate::player::{BrawlerData, Player};
+
+/// Name of gold in resources.csv.
+const GOLD: &str = "Gold";
+
+/// Box tokens a battle is worth, from globals.csv.
+pub fn battle_tokens(result: BattleResult, multiplier: u32) -> u32 {
+    let globals = &game_data().globals;
+
+    let tokens = match result {
+        BattleResult::Victory => globals.tokens_for_result[0],
+        BattleResult::Draw => globals.tokens_for_result[1],
+        BattleResult::Defeat => globals.tokens_for_result[2],
+    };
+
+    (tokens.max(0) as u32).saturating_mul(multiplier)
+}
+
+/// Why a box could not be opened.
+#[derive(Debug)]
+pub enum BoxError {
+    UnknownBox,
+    CannotAfford,
+}
+
+/// Brawlers of a rarity the player does not have yet, as unlock cards.
+fn locked(player: &Player, rarity: &str, picked: &[usize]) -> Vec<usize> {
+    Cards::get_brawlers_with_rarity(rarity).into_iter()
+        .filter(|&card| !picked.contains(&card))
+        .filter(|&card| Cards::get_brawler_id(card).is_some_and(|id| !player.unlocked_brawlers.contains_key(&(id as i32))))
+        .collect()
+}
+
+/// Rolls for a new brawler, rarest first. Pity raises the chance of every
+/// rarity the player has gone without.
+fn roll_brawler(player: &Player, row: &BoxRow, picked: &[usize], rng: &mut impl Rng) -> Option<usize> {
+    for drop in row.drops.iter().rev() {
+        let cards = locked(player, &drop.rarity, picked);
+        let pity = player.box_pity.get(&drop.rarity).copied().unwrap_or(0);
+
+        if !cards.is_empty() && rng.gen_range(0..10000) < drop.chance + pity * drop.pity_step {
+            return cards.choose(rng).copied();
+        }
+    }
+
+    None
+}
+
+fn unlock(player: &mut Player, card: usize) -> Option<i32> {
+    let id = Cards::get_brawler_id(card)? as i32;
+
+    player.unlocked_brawlers.insert(id, BrawlerData {
+        cards: HashMap::from([(card as i32, 1)]),
+        skins: Vec::new(),
+        selected: 0,
+        trophies: 0,
+        highest_trophies: 0,
+        level: 0,
+        power_points: 0,
+        state: 2,
+        star_power: 0,
+        claimed_rank: 1,
+    });
+
+    Some(id)
+}
+
+/// Charges the player for a box and gives them what it drops, returning
+/// the items to deliver.
+pub fn open(player: &mut Player, box_type: i32, rng: &mut impl Rng) -> Result<Vec<DeliveryItem>, BoxError> {
+    let data = game_data();
+    let row = data.box_by_type(box_type).ok_or(BoxError::UnknownBox)?;
+    let gold_ref = DataRef::new(RESOURCES, data.table("resources").and_then(|table| table.find(GOLD)).map_or(0, |row| row.id) as i32);
+
+    if !player.spend(&row.cost_resource, row.cost.max(0) as u32) {
+        return Err(BoxError::CannotAfford);
+    }
+
+    let mut items = Vec::new();
+    let mut picked = Vec::new();
+
+    for _ in 0..row.rolls {
+        if let Some(card) = roll_brawler(player, row, &picked, rng) {
+            if let Some(id) = unlock(player, card) {
+                picked.push(card);
+                items.push(DeliveryItem { item_type: ITEM_BRAWLER, amount: 1, data: DataRef::new(CHARACTERS, id) });
+                continue;
+            }
+        }
+
+        let owned: Vec<i32> = player.unlocked_brawlers.keys().copied().collect();
+        let amount = rng.gen_range(row.power_points.0..=row.power_points.1.max(row.power_points.0));
+
+        match owned.choose(rng) {
+            Some(&id) => {
+                player.unlocked_brawlers.get_mut(&id).unwrap().power_points += amount;
+                items.push(DeliveryItem { item_type: ITEM_POWER_POINTS, amount, data: DataRef::new(CHARACTERS, id) });
+            }
+            // nothing to power up yet, pay the points out as gold
+            None => {
+                player.gold = player.gold.saturating_add_signed(amount);
+                items.push(DeliveryItem { item_type: ITEM_GOLD, amount, data: gold_ref });
+            }
+        }
+    }
+
+    let gold = rng.gen_range(row.gold.0..=row.gold.1.max(row.gold.0));
+    player.gold = player.gold.saturating_add_signed(gold);
+    items.push(DeliveryItem { item_type: ITEM_GOLD, amount: gold, data: gold_ref });
+
+    for drop in &row.drops {
+        let got = picked.iter().any(|&card| Cards::get_brawler_rarity(card).as_deref() == Some(drop.rarity.as_str()));
+
+        if got || locked(player, &drop.rarity, &[]).is_empty() {
+            player.box_pity.remove(&drop.rarity);
+        } else {
+            *player.box_pity.entry(drop.rarity.clone()).or_insert(0) += 1;
+        }
+    }
+
+    player.mark_dirty();
+
+    info!("deuce: player {} opened a {} with {} new brawlers", player.id, row.name, picked.len());
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    const BRAWL_BOX: i32 = 10;
+    const BIG_BOX: i32 = 12;
+
+    fn gold_ref() -> DataRef {
+        let data = game_data();
+        DataRef::new(RESOURCES, data.table("resources").unwrap().find("Gold").unwrap().id as i32)
+    }
+
+    #[test]
+    fn battles_earn_tokens() {
+        let globals = &game_data().globals;
+
+        assert_eq!(battle_tokens(BattleResult::Victory, 1), globals.tokens_for_result[0] as u32);
+        assert_eq!(battle_tokens(BattleResult::Defeat, 2), 2 * globals.tokens_for_result[2] as u32);
+        assert_eq!(battle_tokens(BattleResult::Victory, u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn boxes_cost_their_price() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut player = Player::new();
+        player.tokens = 150;
+
+        assert!(open(&mut player, BRAWL_BOX, &mut rng).is_ok());
+        assert_eq!(player.tokens, 50);
+
+        assert!(matches!(open(&mut player, BRAWL_BOX, &mut rng), Err(BoxError::CannotAfford)));
+        assert!(matches!(open(&mut player, 99, &mut rng), Err(BoxError::UnknownBox)));
+        assert_eq!(player.tokens, 50);
+    }
+
+    #[test]
+    fn pity_grows_until_a_rarity_drops() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut player = Player::new();
+        player.tokens = 100;
+
+        let items = open(&mut player, BRAWL_BOX, &mut rng).unwrap();
+        let dropped: Vec<String> = items.iter()
+            .filter(|item| item.item_type == ITEM_BRAWLER)
+            .map(|item| game_data().characters[item.data.instance_id as usize].name.clone())
+            .collect();
+
+        // every rarity that did not drop is one step more likely next time
+        for drop in &game_data().box_by_type(BRAWL_BOX).unwrap().drops {
+            let got = player.unlocked_brawlers.keys().any(|&id| {
+                Cards::get_brawlers_with_rarity(&drop.rarity).into_iter().any(|card| Cards::get_brawler_id(card) == Some(id as usize))
+            });
+
+            assert_eq!(player.box_pity.get(&drop.rarity).copied(), if got { None } else { Some(1) }, "{}: {:?}", drop.rarity, dropped);
+        }
+
+        // enough pity makes a legendary certain, and resets once it drops
+        player.box_pity.insert("legendary".to_string(), 10000);
+        player.tokens = 100;
+
+        let items = open(&mut player, BRAWL_BOX, &mut rng).unwrap();
+        let card = Cards::get_brawlers_with_rarity("legendary").into_iter()
+            .find(|&card| items.iter().any(|item| item.item_type == ITEM_BRAWLER && Some(item.data.instance_id as usize) == Cards::get_brawler_id(card)));
+
+        assert!(card.is_some(), "no legendary in the box");
+        assert!(!player.box_pity.contains_key("legendary"));
+    }
+
+    #[test]
+    fn same_seed_same_box() {
+        let contents = || {
+            let mut player = Player::new();
+            player.gems = 30;
+
+            open(&mut player, BIG_BOX, &mut StdRng::seed_from_u64(42)).unwrap().iter()
+                .map(|item| (item.item_type, item.amount, item.data))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(contents(), contents());
+    }
+
+    #[test]
+    fn power_points_once_every_brawler_is_owned() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut player = Player::new();
+
+        for card in game_data().cards.iter().filter(|card| card.is_unlock()).map(|card| card.id) {
+            unlock(&mut player, card);
+        }
+
+        player.box_pity.insert("rare".to_string(), 3);
+        player.gems = 30;
+        let gold = player.gold;
+
+        let items = open(&mut player, BIG_BOX, &mut rng).unwrap();
+        let (last, rolls) = items.split_last().unwrap();
+
+        assert_eq!(rolls.len(), 3);
+        assert!(rolls.iter().all(|item| item.item_type == ITEM_POWER_POINTS && (8..=20).contains(&item.amount)));
+        assert_eq!(rolls.iter().map(|item| item.amount).sum::<i32>(), player.unlocked_brawlers.values().map(|brawler| brawler.power_points).sum::<i32>());
+
+        assert_eq!((last.item_type, last.data), (ITEM_GOLD, gold_ref()));
+        assert_eq!(player.gold, gold + last.amount as u32);
+
+        // nothing left to pity
+        assert!(player.box_pity.is_empty());
+    }
+
+    #[test]
+    fn points_pay_out_as_gold_without_brawlers() {
+        let mut player = Player::new();
+        player.gems = 30;
+
+        // negative pity keeps every brawler out of the box
+        for drop in &game_data().box_by_type(BIG_BOX).unwrap().drops {
+            player.box_pity.insert(drop.rarity.clone(), -1000);
+        }
+
+        let items = open(&mut player, BIG_BOX, &mut StdRng::seed_from_u64(7)).unwrap();
+
+        assert_eq!(items.len(), 4);
+        assert!(items.iter().all(|item| item.item_type == ITEM_GOLD && item.data == gold_ref()));
+        assert!(player.unlocked_brawlers.is_empty());
+    }
+}
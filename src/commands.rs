@@ -0,0 +1,224 @@
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use crate::csv_logic::{GameData, DataError, DATA_DIR};
+use crate::database::{self, DatabaseError, PlayerExport, Snapshot, Storage};
+use crate::events;
+use crate::experience;
+use crate::logic_long::LogicLong;
+use crate::player::Player;
+use crate::settings::Settings;
+use crate::render;
+use crate::token;
+
+pub const USAGE: &str = r#"usage: deuce [command]
+
+without a command the server is started.
+
+commands:
+    reset-token <player>            issue a new token, printed once
+    export-player <player> [file]   write the account as json, to stdout without a file
+    import-player <file>            create the exported account under a new id
+    delete-player <player>          delete the account with its chat and club references
+    snapshot <file>                 copy every table into a json archive
+    restore <file> [--replace]      load an archive into an empty database, or wipe it first
+    check-data                      validate the game data in assets/csv_logic and the event plan
+    render-map <map> [file]         print a map of maps.csv, and draw it to a .png or .svg file
+
+<player> is a #TAG, a <high>-<low> id or token:<token>."#;
+
+/// Runs an admin command. Returns false if the arguments are not a command.
+pub fn run(settings: &Settings, args: &[&str]) -> bool {
+    let result = match args {
+        ["reset-token", player] => reset_token(settings, player),
+        ["export-player", player] => export_player(settings, player, None),
+        ["export-player", player, file] => export_player(settings, player, Some(file)),
+        ["import-player", file] => import_player(settings, file),
+        ["delete-player", player] => delete_player(settings, player),
+        ["snapshot", file] => snapshot(settings, file),
+        ["restore", file] => restore(settings, file, false),
+        ["restore", file, "--replace"] => restore(settings, file, true),
+        ["check-data"] => check_data(settings),
+        ["render-map", map] => render_map(map, None),
+        ["render-map", map, file] => render_map(map, Some(file)),
+        _ => return false,
+    };
+
+    if let Err(e) = result {
+        eprintln!("deuce: {}", e);
+        std::process::exit(1);
+    }
+
+    true
+}
+
+fn open_database(settings: &Settings) -> Result<Arc<dyn Storage>, String> {
+    let database = database::open(settings).map_err(|e| e.to_string())?;
+    database.migrate().map_err(|e| e.to_string())?;
+
+    Ok(database)
+}
+
+/// Looks up the account a `<player>` argument refers to.
+fn find_player(database: &dyn Storage, selector: &str) -> Result<LogicLong, String> {
+    let found = if let Some(raw) = selector.strip_prefix("token:") {
+        let mut player = Player::new();
+        player.token = Some(token::hash(raw));
+
+        match database.load_player(&player) {
+            Ok(info) => Some(info.id),
+            Err(DatabaseError::NotFound) => None,
+            Err(e) => return Err(e.to_string()),
+        }
+    } else if let Some((high, low)) = selector.split_once('-') {
+        let high = high.parse().map_err(|_| format!("invalid id {}", selector))?;
+        let low = low.parse().map_err(|_| format!("invalid id {}", selector))?;
+
+        database.find_player_by_id(LogicLong::new(high, low)).map_err(|e| e.to_string())?.map(|info| info.id)
+    } else {
+        database.find_player_by_tag(selector).map_err(|e| e.to_string())?.map(|info| info.id)
+    };
+
+    found.ok_or_else(|| format!("no player {}", selector))
+}
+
+/// Issues a new token for an account, e.g. for a player who lost their
+/// device. The old token stops working and the new one is printed once.
+fn reset_token(settings: &Settings, selector: &str) -> Result<(), String> {
+    let database = open_database(settings)?;
+    let id = find_player(database.as_ref(), selector)?;
+
+    let new_token = token::generate();
+    database.set_token(id, &token::hash(&new_token)).map_err(|e| e.to_string())?;
+
+    println!("{} {}", id, new_token);
+    Ok(())
+}
+
+fn export_player(settings: &Settings, selector: &str, file: Option<&str>) -> Result<(), String> {
+    let database = open_database(settings)?;
+    let id = find_player(database.as_ref(), selector)?;
+
+    let export = database::export_player(database.as_ref(), id).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+
+    match file {
+        Some(file) => fs::write(file, json).map_err(|e| format!("failed to write {}: {}", file, e))?,
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+fn import_player(settings: &Settings, file: &str) -> Result<(), String> {
+    let json = fs::read_to_string(file).map_err(|e| format!("failed to read {}: {}", file, e))?;
+    let export: PlayerExport = serde_json::from_str(&json).map_err(|e| format!("invalid export {}: {}", file, e))?;
+
+    let database = open_database(settings)?;
+
+    let id = match database::import_player(database.as_ref(), &export) {
+        Err(DatabaseError::AlreadyExists) => return Err("an account with this token already exists".to_string()),
+        result => result.map_err(|e| e.to_string())?,
+    };
+
+    println!("{} imported as {}", export.player.id, id);
+    Ok(())
+}
+
+fn delete_player(settings: &Settings, selector: &str) -> Result<(), String> {
+    let database = open_database(settings)?;
+    let id = find_player(database.as_ref(), selector)?;
+
+    database.delete_player(id).map_err(|e| e.to_string())?;
+
+    println!("{} deleted", id);
+    Ok(())
+}
+
+fn snapshot(settings: &Settings, file: &str) -> Result<(), String> {
+    let database = open_database(settings)?;
+
+    let snapshot = database.snapshot().map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&snapshot).map_err(|e| e.to_string())?;
+
+    fs::write(file, json).map_err(|e| format!("failed to write {}: {}", file, e))?;
+
+    let rows: usize = snapshot.tables.values().map(Vec::len).sum();
+    println!("{} rows at schema version {} written to {}", rows, snapshot.schema_version, file);
+
+    Ok(())
+}
+
+fn restore(settings: &Settings, file: &str, replace: bool) -> Result<(), String> {
+    let json = fs::read_to_string(file).map_err(|e| format!("failed to read {}: {}", file, e))?;
+    let snapshot: Snapshot = serde_json::from_str(&json).map_err(|e| format!("invalid snapshot {}: {}", file, e))?;
+
+    let database = open_database(settings)?;
+
+    match database.restore(&snapshot, replace) {
+        Err(DatabaseError::AlreadyExists) => return Err("database is not empty, pass --replace to wipe it".to_string()),
+        result => result.map_err(|e| e.to_string())?,
+    }
+
+    println!("restored {} taken at {}", file, snapshot.taken_at);
+    Ok(())
+}
+
+fn check_data(settings: &Settings) -> Result<(), String> {
+    let data = GameData::load(DATA_DIR).map_err(|e| e.to_string())?;
+
+    let mut problems = match data.validate() {
+        Ok(()) => Vec::new(),
+        Err(DataError::Invalid(problems)) => problems,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    problems.extend(experience::check_gates(&data));
+    problems.extend(events::check_plan(&settings.events, &data));
+
+    if problems.is_empty() {
+        println!("{} tables in {} and the event plan are valid", data.tables().count(), DATA_DIR);
+        return Ok(());
+    }
+
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+
+    Err(format!("{} problems found", problems.len()))
+}
+
+fn render_map(name: &str, file: Option<&str>) -> Result<(), String> {
+    let data = GameData::load(DATA_DIR).map_err(|e| e.to_string())?;
+
+    let map = data.map_by_name(name).ok_or_else(|| {
+        let names: Vec<&str> = data.maps.iter().map(|map| map.name.as_str()).collect();
+        format!("no map {} in maps.csv, there are {}", name, names.join(", "))
+    })?;
+
+    print!("{}", render::ascii(map, &data));
+
+    match file {
+        Some(file) if file.ends_with(".png") => {
+            let out = fs::File::create(file).map_err(|e| format!("{}: {}", file, e))?;
+            render::png(map, &data, io::BufWriter::new(out)).map_err(|e| format!("{}: {}", file, e))?;
+        }
+        Some(file) if file.ends_with(".svg") => {
+            fs::write(file, render::svg(map, &data)).map_err(|e| format!("{}: {}", file, e))?;
+        }
+        Some(file) => return Err(format!("{}: pick a .png or .svg file", file)),
+        None => {}
+    }
+
+    let uneven: Vec<String> = map.uneven_rows()
+        .map(|(line, width)| format!("maps.csv line {}: row is {} tiles wide, expected {}", line, width, map.width))
+        .collect();
+
+    if uneven.is_empty() {
+        Ok(())
+    } else {
+        println!("{}", uneven.join("\n"));
+        Err(format!("{} rows of {} are not {} tiles wide", uneven.len(), map.name, map.width))
+    }
+}
@@ -0,0 +1,60 @@
+use crate::csv_logic::Row;
+
+/// Resources a box can cost, as named in boxes.csv.
+pub const BOX_COST_RESOURCES: [&str; 3] = ["Gold", "Diamonds", "Tokens"];
+
+/// Chance of a brawler of one rarity on every roll of a box.
+pub struct BrawlerDrop {
+    /// Rarity of the brawler's unlock card in cards.csv.
+    pub rarity: String,
+    /// Chance out of 10000.
+    pub chance: i32,
+    /// Added to the chance for every box opened since the player last got
+    /// a brawler of this rarity.
+    pub pity_step: i32,
+}
+
+/// A row of boxes.csv: what a box costs and what it can drop. Each roll
+/// gives a brawler the player does not have yet or power points, and every
+/// box gives gold on top.
+pub struct BoxRow {
+    pub name: String,
+    /// Box type the client opens the box by.
+    pub box_type: i32,
+    pub cost_resource: String,
+    pub cost: i32,
+    pub rolls: i32,
+    pub gold: (i32, i32),
+    pub power_points: (i32, i32),
+    pub drops: Vec<BrawlerDrop>,
+}
+
+fn ints(row: &Row, column: &str) -> Vec<i32> {
+    row.array(column).iter().map(|value| value.trim().parse().unwrap_or(0)).collect()
+}
+
+impl From<&Row> for BoxRow {
+    fn from(row: &Row) -> Self {
+        let chances = ints(row, "BrawlerChance");
+        let pity_steps = ints(row, "PityStep");
+
+        let drops = row.array("Rarities").iter().enumerate()
+            .map(|(i, rarity)| BrawlerDrop {
+                rarity: rarity.to_string(),
+                chance: chances.get(i).copied().unwrap_or(0),
+                pity_step: pity_steps.get(i).copied().unwrap_or(0),
+            })
+            .collect();
+
+        Self {
+            name: row.name().to_string(),
+            box_type: row.int("Type"),
+            cost_resource: row.get("CostResource").to_string(),
+            cost: row.int("Cost"),
+            rolls: row.int("Rolls"),
+            gold: (row.int("GoldMin"), row.int("GoldMax")),
+            power_points: (row.int("PowerPointsMin"), row.int("PowerPointsMax")),
+            drops,
+        }
+    }
+}
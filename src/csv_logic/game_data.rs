@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use crate::csv_logic::*;
+use crate::csv_logic::validate;
+
+pub const DATA_DIR: &str = "assets/csv_logic";
+
+static GAME_DATA: RwLock<Option<Arc<GameData>>> = RwLock::new(None);
+
+/// Every CSV under [`DATA_DIR`], parsed once and shared read-only by all
+/// sessions.
+pub struct GameData {
+    /// Bumped on every reload, so sessions can tell they are on old data.
+    pub version: u64,
+    tables: HashMap<String, Table>,
+
+    pub cards: Vec<CardRow>,
+    pub characters: Vec<CharacterRow>,
+    pub locations: Vec<LocationRow>,
+    pub tiles: Vec<TileRow>,
+    pub maps: Vec<Map>,
+    pub milestones: Vec<MilestoneTrack>,
+    pub thumbnails: Vec<ThumbnailRow>,
+    pub boxes: Vec<BoxRow>,
+    pub globals: Globals,
+}
+
+impl GameData {
+    pub fn load(dir: &str) -> Result<Self, DataError> {
+        let mut tables = HashMap::new();
+
+        let entries = std::fs::read_dir(dir).map_err(|e| DataError::Io(dir.into(), e))?;
+
+        for entry in entries {
+            let path = entry.map_err(|e| DataError::Io(dir.into(), e))?.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) == Some("csv") {
+                let table = Table::load(&path)?;
+                tables.insert(table.name.clone(), table);
+            }
+        }
+
+        let rows = |name: &str| -> Result<&[Row], DataError> {
+            tables.get(name).map(Table::rows).ok_or_else(|| DataError::Io(
+                Path::new(dir).join(format!("{}.csv", name)),
+                std::io::ErrorKind::NotFound.into()
+            ))
+        };
+
+        let cards = rows("cards")?.iter().map(CardRow::from).collect();
+        let characters = rows("characters")?.iter().map(CharacterRow::from).collect();
+        let locations = rows("locations")?.iter().map(LocationRow::from).collect();
+        let tiles: Vec<TileRow> = rows("tiles")?.iter().map(TileRow::from).collect();
+        let maps = Map::parse_all(rows("maps")?, &tiles);
+        let milestones = rows("milestones")?.iter().map(MilestoneTrack::from).collect();
+        let thumbnails = rows("player_thumbnails")?.iter().map(ThumbnailRow::from).collect();
+        let boxes = rows("boxes")?.iter().map(BoxRow::from).collect();
+        rows("globals")?;
+        let globals = Globals::from(&tables["globals"]);
+
+        Ok(Self { version: 0, tables, cards, characters, locations, tiles, maps, milestones, thumbnails, boxes, globals })
+    }
+
+    /// Checks the tables are fit to serve, listing every problem found.
+    pub fn validate(&self) -> Result<(), DataError> {
+        let problems = validate::check(self);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(DataError::Invalid(problems))
+        }
+    }
+
+    /// A table by its file name without the extension, e.g. `globals`.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.get(name)
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &Table> {
+        self.tables.values()
+    }
+
+    pub fn character_by_name(&self, name: &str) -> Option<&CharacterRow> {
+        self.find("characters", name).map(|id| &self.characters[id])
+    }
+
+    pub fn location_by_name(&self, name: &str) -> Option<&LocationRow> {
+        self.find("locations", name).map(|id| &self.locations[id])
+    }
+
+    /// A map by its group name in maps.csv, e.g. `Gemgrab_1`.
+    pub fn map_by_name(&self, name: &str) -> Option<&Map> {
+        self.maps.iter().find(|map| map.name == name)
+    }
+
+    pub fn milestone_track(&self, name: &str) -> Option<&MilestoneTrack> {
+        self.find("milestones", name).map(|id| &self.milestones[id])
+    }
+
+    /// A box by the type the client opens it by.
+    pub fn box_by_type(&self, box_type: i32) -> Option<&BoxRow> {
+        self.boxes.iter().find(|row| row.box_type == box_type)
+    }
+
+    pub fn tile_by_code(&self, code: char) -> Option<&TileRow> {
+        self.tiles.iter().find(|tile| tile.code == code)
+    }
+
+    fn find(&self, table: &str, name: &str) -> Option<usize> {
+        self.tables.get(table)?.find(name).map(|row| row.id)
+    }
+}
+
+fn load_valid() -> Result<GameData, DataError> {
+    let data = GameData::load(DATA_DIR)?;
+    data.validate()?;
+
+    Ok(data)
+}
+
+/// The current game data. Parsed from [`DATA_DIR`] on first use; the server
+/// calls this at startup so broken data stops it right away. Callers keep
+/// the returned tables for the request they are handling even if a reload
+/// swaps in new ones meanwhile.
+pub fn game_data() -> Arc<GameData> {
+    if let Some(data) = GAME_DATA.read().unwrap().as_ref() {
+        return Arc::clone(data);
+    }
+
+    let mut current = GAME_DATA.write().unwrap();
+    let data = current.get_or_insert_with(|| Arc::new(load_valid().expect("deuce: failed to load game data")));
+
+    Arc::clone(data)
+}
+
+/// Parses [`DATA_DIR`] again and swaps the result in. If the new tables do
+/// not load or validate, the current ones stay in place.
+pub fn reload_game_data() -> Result<Arc<GameData>, DataError> {
+    let mut data = load_valid()?;
+
+    let mut current = GAME_DATA.write().unwrap();
+    data.version = current.as_ref().map_or(0, |old| old.version + 1);
+
+    let data = Arc::new(data);
+    *current = Some(Arc::clone(&data));
+
+    Ok(data)
+}
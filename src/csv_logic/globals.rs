@@ -0,0 +1,70 @@
+use crate::csv_logic::{Row, Table};
+
+/// Globals the server reads, see [`Globals`].
+pub const REQUIRED_GLOBALS: &[&str] = &[
+    "STARTING_DIAMONDS",
+    "STARTING_GOLD",
+    "HOME_CONF_NUMBERS",
+    "HOME_BOX_COSTS_1",
+    "HOME_BOX_COSTS_2",
+    "HOME_BOX_COSTS_3",
+    "EXP_FOR_VICTORY",
+    "EXP_FOR_DRAW",
+    "EXP_FOR_DEFEAT",
+    "EXP_FOR_STAR_PLAYER",
+    "NAME_CHANGE_DIAMOND_COST",
+    "TROPHIES_FOR_VICTORY",
+    "TROPHIES_FOR_DRAW",
+    "TROPHIES_FOR_DEFEAT",
+    "TOKENS_FOR_VICTORY",
+    "TOKENS_FOR_DRAW",
+    "TOKENS_FOR_DEFEAT",
+];
+
+/// How many values the client reads from `HOME_CONF_NUMBERS`.
+pub const HOME_CONF_NUMBERS_LEN: usize = 11;
+
+/// The rows of globals.csv the server uses, by name.
+pub struct Globals {
+    pub starting_diamonds: i32,
+    pub starting_gold: i32,
+    /// Economy numbers of the home data, in the order the client reads them.
+    /// The trophy road length goes between the last two.
+    pub home_conf_numbers: Vec<i32>,
+    /// The three box cost arrays of the home data.
+    pub home_box_costs: [Vec<i32>; 3],
+    /// Experience for winning, drawing and losing a battle.
+    pub exp_for_result: [i32; 3],
+    /// Experience on top of the result for the star player.
+    pub exp_for_star_player: i32,
+    /// Gems a name change costs after the first, free one.
+    pub name_change_cost: i32,
+    /// Trophies the brawler played wins or loses for winning, drawing and
+    /// losing a battle.
+    pub trophies_for_result: [i32; 3],
+    /// Box tokens for winning, drawing and losing a battle.
+    pub tokens_for_result: [i32; 3],
+}
+
+impl From<&Table> for Globals {
+    fn from(table: &Table) -> Self {
+        let number = |name: &str| table.find(name).map_or(0, |row| row.int("NumberValue"));
+        let numbers = |name: &str| table.find(name).map_or_else(Vec::new, number_array);
+
+        Self {
+            starting_diamonds: number("STARTING_DIAMONDS"),
+            starting_gold: number("STARTING_GOLD"),
+            home_conf_numbers: numbers("HOME_CONF_NUMBERS"),
+            home_box_costs: [numbers("HOME_BOX_COSTS_1"), numbers("HOME_BOX_COSTS_2"), numbers("HOME_BOX_COSTS_3")],
+            exp_for_result: [number("EXP_FOR_VICTORY"), number("EXP_FOR_DRAW"), number("EXP_FOR_DEFEAT")],
+            exp_for_star_player: number("EXP_FOR_STAR_PLAYER"),
+            name_change_cost: number("NAME_CHANGE_DIAMOND_COST"),
+            trophies_for_result: [number("TROPHIES_FOR_VICTORY"), number("TROPHIES_FOR_DRAW"), number("TROPHIES_FOR_DEFEAT")],
+            tokens_for_result: [number("TOKENS_FOR_VICTORY"), number("TOKENS_FOR_DRAW"), number("TOKENS_FOR_DEFEAT")],
+        }
+    }
+}
+
+fn number_array(row: &Row) -> Vec<i32> {
+    row.array("NumberArray").iter().map(|value| value.trim().parse().unwrap_or(0)).collect()
+}
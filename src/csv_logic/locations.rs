@@ -1,29 +1,35 @@
-use std::error::Error;
-use csv::ReaderBuilder;
+use crate::csv_logic::{game_data, Row};
 
-pub struct Locations;
+/// A row of locations.csv: an event slot's mode and the maps it uses.
+pub struct LocationRow {
+    pub id: usize,
+    pub name: String,
+    pub tid: String,
+    pub game_mode: String,
+    /// Group name of the maps in maps.csv.
+    pub allowed_maps: String,
+}
 
-impl Locations {
-    /// Returns a vector of location IDs (0-indexed, computed as line index minus 2)
-    /// for rows in the CSV where the first column is not "Tutorial".
-    pub fn get_locations() -> Result<Vec<usize>, Box<dyn Error>> {
-        let mut rdr = ReaderBuilder::new()
-            .has_headers(false)
-            .from_path("assets/csv_logic/locations.csv")?;
-        let mut locations_id = Vec::new();
-        // Enumerate gives the line count starting at 0.
-        for (i, record) in rdr.records().enumerate() {
-            let record = record?;
-            // Skip the two header rows.
-            if i < 2 {
-                continue;
-            }
-            // If the first field is not "Tutorial", add (i - 2) to the vector.
-            if record.get(0).unwrap_or("") != "Tutorial" {
-                locations_id.push(i - 2);
-            }
+impl From<&Row> for LocationRow {
+    fn from(row: &Row) -> Self {
+        Self {
+            id: row.id,
+            name: row.name().to_string(),
+            tid: row.get("TID").to_string(),
+            game_mode: row.get("GameMode").to_string(),
+            allowed_maps: row.get("AllowedMaps").to_string(),
         }
-        Ok(locations_id)
     }
 }
 
+pub struct Locations;
+
+impl Locations {
+    /// Returns every location id except the tutorial.
+    pub fn get_locations() -> Vec<usize> {
+        game_data().locations.iter()
+            .filter(|location| location.name != "Tutorial")
+            .map(|location| location.id)
+            .collect()
+    }
+}
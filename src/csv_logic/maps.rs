@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::csv_logic::{Row, TileRow};
+
+/// Side of a map tile in world units.
+pub const TILE_SIZE: i32 = 300;
+
+/// Tile code of open ground, which markers stand on.
+pub const OPEN_TILE: char = '.';
+
+/// Something placed on a map besides its tiles, written as a digit in
+/// maps.csv.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    /// Where a team's brawlers start, `1` or `2`. Showdown maps only use `1`.
+    Spawn(u8),
+    /// Any other digit: boxes, safes or goals depending on the mode.
+    Objective(u8),
+}
+
+impl Marker {
+    pub fn from_code(code: char) -> Option<Self> {
+        match code.to_digit(10)? as u8 {
+            team @ (1 | 2) => Some(Marker::Spawn(team)),
+            digit => Some(Marker::Objective(digit)),
+        }
+    }
+}
+
+pub struct Cell {
+    pub code: char,
+    /// Id of the tile in tiles.csv.
+    pub tile: usize,
+    pub marker: Option<Marker>,
+}
+
+/// A map group of maps.csv parsed into a grid, row by row from the top.
+pub struct Map {
+    /// Group name, as referenced by locations.csv.
+    pub name: String,
+    /// Line of maps.csv the map starts on.
+    pub line: usize,
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+    /// File line and width of every row.
+    rows: Vec<(usize, usize)>,
+}
+
+impl Map {
+    /// Parses every map of maps.csv. Codes without a tile and missing cells
+    /// of short rows read as open ground; validation reports both.
+    pub fn parse_all(rows: &[Row], tiles: &[TileRow]) -> Vec<Map> {
+        let codes: HashMap<char, usize> = tiles.iter().map(|tile| (tile.code, tile.id)).collect();
+        let open = codes.get(&OPEN_TILE).copied().unwrap_or(0);
+
+        groups(rows).into_iter().map(|group| {
+            let lines: Vec<Vec<char>> = group.iter().map(|row| row.get("Data").chars().collect()).collect();
+            let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+
+            let cells = lines.iter()
+                .flat_map(|line| (0..width).map(|x| line.get(x).copied().unwrap_or(OPEN_TILE)))
+                .map(|code| match Marker::from_code(code) {
+                    Some(marker) => Cell { code, tile: open, marker: Some(marker) },
+                    None => Cell { code, tile: codes.get(&code).copied().unwrap_or(open), marker: None },
+                })
+                .collect();
+
+            Map {
+                name: group[0].get("Group").to_string(),
+                line: group[0].line,
+                width,
+                height: lines.len(),
+                cells,
+                rows: group.iter().zip(&lines).map(|(row, line)| (row.line, line.len())).collect(),
+            }
+        }).collect()
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x < self.width {
+            self.cells.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Every cell with its `x` and `y`.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, &Cell)> {
+        self.cells.iter().enumerate().map(|(i, cell)| (i % self.width, i / self.width, cell))
+    }
+
+    pub fn markers(&self) -> impl Iterator<Item = (usize, usize, Marker)> + '_ {
+        self.cells().filter_map(|(x, y, cell)| cell.marker.map(|marker| (x, y, marker)))
+    }
+
+    /// Spawn points of a team, top to bottom.
+    pub fn spawns(&self, team: u8) -> Vec<(usize, usize)> {
+        self.markers()
+            .filter(|(_, _, marker)| *marker == Marker::Spawn(team))
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    /// File line and width of the rows narrower than the map.
+    pub fn uneven_rows(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rows.iter().copied().filter(|&(_, width)| width != self.width)
+    }
+
+    /// World position of the center of a tile.
+    pub fn position(x: usize, y: usize) -> (i32, i32) {
+        (x as i32 * TILE_SIZE + TILE_SIZE / 2, y as i32 * TILE_SIZE + TILE_SIZE / 2)
+    }
+}
+
+/// Splits the rows of maps.csv into groups: every row naming a group starts
+/// one. Groups without data, like the closing `end`, are dropped.
+fn groups(rows: &[Row]) -> Vec<&[Row]> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+
+    for i in 1..=rows.len() {
+        if i == rows.len() || !rows[i].get("Group").is_empty() {
+            let group = &rows[start..i];
+
+            if group.iter().any(|row| !row.get("Data").is_empty()) {
+                groups.push(group);
+            }
+
+            start = i;
+        }
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_logic::Table;
+
+    const TILES: &str = "\"Name\",\"TileCode\"\n\"string\",\"string\"\n\"Open\",\".\"\n\"Wall\",\"M\"\n";
+
+    const MAPS: &str = "\
+\"CodeName\",\"Group\",\"Data\"
+\"String\",\"String\",\"String\"
+1,\"First\",\"M1.\"
+2,,\"..2\"
+3,,\"3M\"
+4,\"Second\",\".1?\"
+5,\"end\",
+";
+
+    fn parse() -> Vec<Map> {
+        let tiles = Table::parse("tiles".to_string(), TILES.as_bytes()).unwrap();
+        let tiles: Vec<TileRow> = tiles.rows().iter().map(TileRow::from).collect();
+        let maps = Table::parse("maps".to_string(), MAPS.as_bytes()).unwrap();
+
+        Map::parse_all(maps.rows(), &tiles)
+    }
+
+    #[test]
+    fn groups_and_sizes() {
+        let maps = parse();
+
+        assert_eq!(maps.len(), 2);
+        assert_eq!((maps[0].name.as_str(), maps[0].line, maps[0].width, maps[0].height), ("First", 3, 3, 3));
+        assert_eq!((maps[1].name.as_str(), maps[1].line, maps[1].width, maps[1].height), ("Second", 6, 3, 1));
+    }
+
+    #[test]
+    fn cells_and_markers() {
+        let map = &parse()[0];
+
+        assert_eq!(map.cell(0, 0).map(|cell| cell.tile), Some(1));
+        assert_eq!(map.cell(1, 0).map(|cell| (cell.tile, cell.marker)), Some((0, Some(Marker::Spawn(1)))));
+        assert_eq!(map.cell(0, 2).and_then(|cell| cell.marker), Some(Marker::Objective(3)));
+        assert!(map.cell(3, 0).is_none());
+
+        assert_eq!(map.spawns(1), [(1, 0)]);
+        assert_eq!(map.spawns(2), [(2, 1)]);
+    }
+
+    #[test]
+    fn short_rows_and_unknown_codes_read_as_open_ground() {
+        let maps = parse();
+
+        assert_eq!(maps[0].cell(2, 2).map(|cell| (cell.code, cell.tile)), Some((OPEN_TILE, 0)));
+        assert_eq!(maps[0].uneven_rows().collect::<Vec<_>>(), [(5, 2)]);
+        assert_eq!(maps[1].cell(2, 0).map(|cell| (cell.code, cell.tile)), Some(('?', 0)));
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(Map::position(0, 0), (150, 150));
+        assert_eq!(Map::position(2, 1), (750, 450));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::csv_logic::Row;
+
+pub const BRAWLER_RANK: &str = "BrawlerRank";
+pub const EXPERIENCE_LEVEL: &str = "ExperienceLevel";
+pub const TROPHY_ROAD: &str = "TrophyRoad";
+
+/// Tracks the server reads.
+pub const REQUIRED_TRACKS: [&str; 3] = [BRAWLER_RANK, EXPERIENCE_LEVEL, TROPHY_ROAD];
+
+/// A row of milestones.csv: a track of thresholds with a reward for every
+/// step. Thresholds past the listed ones grow by `ExtraStep`, which itself
+/// grows by `StepIncrease` every step. Reward arrays repeat their last value.
+pub struct MilestoneTrack {
+    pub id: usize,
+    pub name: String,
+    /// Milestone type the client knows the track by, 0 for tracks only the
+    /// server uses.
+    pub milestone_type: i32,
+    thresholds: Vec<i32>,
+    extra_step: i32,
+    step_increase: i32,
+    /// Number of steps, 0 for one per rank up to `max_rank`.
+    pub steps: usize,
+    reward_types: Vec<i32>,
+    reward_amounts: Vec<i32>,
+    reward_resources: Vec<String>,
+}
+
+pub struct Reward {
+    /// Reward type as the client shows it.
+    pub reward_type: i32,
+    pub amount: i32,
+    /// Name of the resource in resources.csv.
+    pub resource: String,
+}
+
+/// One step of a track: the value range it covers and what completing it
+/// gives.
+pub struct Milestone {
+    pub index: usize,
+    pub start: i32,
+    pub progress: i32,
+    pub reward: Reward,
+}
+
+fn ints(row: &Row, column: &str) -> Vec<i32> {
+    row.array(column).iter().map(|value| value.trim().parse().unwrap_or(0)).collect()
+}
+
+impl From<&Row> for MilestoneTrack {
+    fn from(row: &Row) -> Self {
+        Self {
+            id: row.id,
+            name: row.name().to_string(),
+            milestone_type: row.int("Type"),
+            thresholds: ints(row, "Thresholds"),
+            extra_step: row.int("ExtraStep"),
+            step_increase: row.int("StepIncrease"),
+            steps: row.int("Steps").max(0) as usize,
+            reward_types: ints(row, "RewardType"),
+            reward_amounts: ints(row, "RewardAmount"),
+            reward_resources: row.array("RewardResource").iter().map(|value| value.to_string()).collect(),
+        }
+    }
+}
+
+fn nth<T: Clone + Default>(values: &[T], index: usize) -> T {
+    values.get(index).or(values.last()).cloned().unwrap_or_default()
+}
+
+impl MilestoneTrack {
+    /// Number of steps with ranks capped at `max_rank`.
+    pub fn step_count(&self, max_rank: u32) -> usize {
+        if self.steps > 0 {
+            self.steps
+        } else {
+            max_rank.saturating_sub(1) as usize
+        }
+    }
+
+    /// Value at which step `index` starts, and the previous one completes.
+    pub fn threshold(&self, index: usize) -> i32 {
+        match self.thresholds.get(index) {
+            Some(&threshold) => threshold,
+            None => {
+                let listed = self.thresholds.len().max(1);
+                let last = self.thresholds.last().copied().unwrap_or(0);
+                let extra = (index + 1 - listed) as i32;
+
+                last + self.extra_step * extra + self.step_increase * extra * (extra - 1) / 2
+            }
+        }
+    }
+
+    pub fn milestone(&self, index: usize) -> Milestone {
+        let start = self.threshold(index);
+
+        Milestone {
+            index,
+            start,
+            progress: self.threshold(index + 1) - start,
+            reward: Reward {
+                reward_type: nth(&self.reward_types, index),
+                amount: nth(&self.reward_amounts, index),
+                resource: nth(&self.reward_resources, index),
+            },
+        }
+    }
+
+    /// How many of the first `count` steps `value` completes.
+    pub fn level(&self, value: i32, count: usize) -> usize {
+        (0..count).take_while(|&index| self.threshold(index + 1) <= value).count()
+    }
+
+    /// Steps whose reward has no type, which the client cannot show.
+    pub fn untyped_rewards(&self) -> Vec<usize> {
+        if self.reward_types.is_empty() {
+            return vec![0];
+        }
+
+        self.reward_types.iter().enumerate().filter(|(_, &reward_type)| reward_type == 0).map(|(index, _)| index).collect()
+    }
+
+    /// Listed thresholds that are not above the one before, with their
+    /// index.
+    pub fn decreasing(&self) -> Vec<(usize, i32)> {
+        self.thresholds.windows(2).enumerate()
+            .filter(|(_, pair)| pair[1] <= pair[0])
+            .map(|(i, pair)| (i + 1, pair[1]))
+            .collect()
+    }
+
+    /// Steps completed by going from `from` to `to`.
+    pub fn crossed(&self, from: i32, to: i32, count: usize) -> Vec<Milestone> {
+        (self.level(from, count)..self.level(to, count)).map(|index| self.milestone(index)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv_logic::game_data;
+    use super::*;
+
+    #[test]
+    fn thresholds_extrapolate() {
+        let data = game_data();
+        let track = data.milestone_track(EXPERIENCE_LEVEL).unwrap();
+
+        // one listed threshold, then steps of 40, 50, 60...
+        assert_eq!((0..4).map(|index| track.threshold(index)).collect::<Vec<_>>(), [0, 40, 90, 150]);
+
+        let track = data.milestone_track(TROPHY_ROAD).unwrap();
+
+        // listed up to 2000, then 500 more every step
+        assert_eq!((9..12).map(|index| track.threshold(index)).collect::<Vec<_>>(), [2000, 2500, 3000]);
+    }
+
+    #[test]
+    fn levels_count_completed_steps() {
+        let data = game_data();
+        let track = data.milestone_track(EXPERIENCE_LEVEL).unwrap();
+
+        assert_eq!(track.level(39, 10), 0);
+        assert_eq!(track.level(40, 10), 1);
+        assert_eq!(track.level(150, 10), 3);
+        assert_eq!(track.level(150, 2), 2);
+    }
+
+    #[test]
+    fn crossing_steps() {
+        let data = game_data();
+        let track = data.milestone_track(TROPHY_ROAD).unwrap();
+        let indexes = |from, to, count| track.crossed(from, to, count).iter().map(|milestone| milestone.index).collect::<Vec<_>>();
+
+        assert_eq!(indexes(40, 120, 20), [0, 1]);
+        assert!(indexes(50, 99, 20).is_empty());
+        assert!(indexes(120, 40, 20).is_empty());
+        assert_eq!(indexes(0, 5000, 3), [0, 1, 2]);
+    }
+
+    #[test]
+    fn rewards_repeat_their_last_value() {
+        let data = game_data();
+        let track = data.milestone_track(TROPHY_ROAD).unwrap();
+
+        let first = track.milestone(0);
+        assert_eq!((first.start, first.progress, first.reward.reward_type, first.reward.amount), (0, 50, 1, 20));
+        assert_eq!(first.reward.resource, "Gold");
+
+        let past = track.milestone(15);
+        assert_eq!((past.reward.reward_type, past.reward.amount, past.reward.resource.as_str()), (1, 200, "Gold"));
+    }
+}
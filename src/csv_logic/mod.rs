@@ -1,10 +1,24 @@
+mod table;
+mod game_data;
+mod validate;
 mod cards;
 mod characters;
 mod locations;
-mod skins;
+mod globals;
+mod tiles;
+mod maps;
+mod milestones;
+mod thumbnails;
+mod boxes;
 
+pub use table::*;
+pub use game_data::*;
 pub use cards::*;
 pub use characters::*;
 pub use locations::*;
-pub use skins::*;
-
+pub use globals::*;
+pub use tiles::*;
+pub use maps::*;
+pub use milestones::*;
+pub use thumbnails::*;
+pub use boxes::*;
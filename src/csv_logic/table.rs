@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use csv::ReaderBuilder;
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(PathBuf, std::io::Error),
+    Csv(PathBuf, csv::Error),
+    /// The file is missing its header or type row.
+    MissingHeader(PathBuf),
+    /// The tables parsed but do not fit together.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            DataError::Csv(path, e) => write!(f, "{}: {}", path.display(), e),
+            DataError::MissingHeader(path) => write!(f, "{}: missing header or type row", path.display()),
+            DataError::Invalid(problems) => write!(f, "invalid game data: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+/// Type of a column, as declared in the second row of every CSV.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    String,
+    Int,
+    Boolean,
+}
+
+impl ColumnType {
+    fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "int" => ColumnType::Int,
+            "boolean" => ColumnType::Boolean,
+            _ => ColumnType::String,
+        }
+    }
+}
+
+/// Column names and types of a table, shared by all of its rows.
+#[derive(Debug)]
+pub struct Columns {
+    names: Vec<String>,
+    types: Vec<ColumnType>,
+    index: HashMap<String, usize>,
+}
+
+impl Columns {
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    pub fn column_type(&self, name: &str) -> Option<ColumnType> {
+        self.position(name).map(|i| self.types[i])
+    }
+}
+
+/// One entry of a table. Lines that follow it with an empty first column
+/// are continuation lines holding further values of array columns.
+#[derive(Debug)]
+pub struct Row {
+    /// Instance id of the row, counting only rows with a name.
+    pub id: usize,
+    /// Line of the file the row starts on, counting from 1.
+    pub line: usize,
+    columns: Arc<Columns>,
+    lines: Vec<Vec<String>>,
+}
+
+impl Row {
+    pub fn name(&self) -> &str {
+        self.lines[0].first().map(String::as_str).unwrap_or("")
+    }
+
+    /// Raw value of a column, empty if the column does not exist.
+    pub fn get(&self, column: &str) -> &str {
+        self.cells(column).next().map_or("", |(_, value)| value)
+    }
+
+    /// Value of an int column. Empty cells read as 0.
+    pub fn int(&self, column: &str) -> i32 {
+        self.get(column).trim().parse().unwrap_or(0)
+    }
+
+    pub fn bool(&self, column: &str) -> bool {
+        self.get(column).trim().eq_ignore_ascii_case("true")
+    }
+
+    /// Values of an array column over this row and its continuation lines.
+    pub fn array(&self, column: &str) -> Vec<&str> {
+        self.cells(column).map(|(_, value)| value).filter(|value| !value.is_empty()).collect()
+    }
+
+    /// Every cell of a column with the file line it is on, including the
+    /// continuation lines.
+    pub fn cells(&self, column: &str) -> impl Iterator<Item = (usize, &str)> {
+        let position = self.columns.position(column);
+
+        self.lines.iter().enumerate().filter_map(move |(i, line)| {
+            line.get(position?).map(|value| (self.line + i, value.as_str()))
+        })
+    }
+}
+
+/// A parsed CSV file from `assets/csv_logic`.
+#[derive(Debug)]
+pub struct Table {
+    pub name: String,
+    columns: Arc<Columns>,
+    rows: Vec<Row>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Table {
+    pub fn load(path: &Path) -> Result<Self, DataError> {
+        let file = std::fs::File::open(path).map_err(|e| DataError::Io(path.to_path_buf(), e))?;
+        let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("").to_string();
+
+        Self::parse(name, file).map_err(|e| match e {
+            DataError::Csv(_, e) => DataError::Csv(path.to_path_buf(), e),
+            DataError::MissingHeader(_) => DataError::MissingHeader(path.to_path_buf()),
+            e => e,
+        })
+    }
+
+    pub fn parse<R: Read>(name: String, reader: R) -> Result<Self, DataError> {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+
+        let mut records = rdr.records();
+        let mut next = || records.next().transpose().map_err(|e| DataError::Csv(PathBuf::from(&name), e));
+
+        let header = next()?.ok_or_else(|| DataError::MissingHeader(PathBuf::from(&name)))?;
+        let types = next()?.ok_or_else(|| DataError::MissingHeader(PathBuf::from(&name)))?;
+
+        let names: Vec<String> = header.iter().map(String::from).collect();
+        let index = names.iter().enumerate().map(|(i, name)| (name.clone(), i)).collect();
+        let types = (0..names.len()).map(|i| ColumnType::parse(types.get(i).unwrap_or(""))).collect();
+
+        let columns = Arc::new(Columns { names, types, index });
+
+        let mut rows: Vec<Row> = Vec::new();
+        let mut by_name = HashMap::new();
+
+        // header and type rows come first
+        let mut line_number = 2;
+
+        while let Some(record) = next()? {
+            line_number += 1;
+
+            let line: Vec<String> = record.iter().map(String::from).collect();
+
+            match rows.last_mut() {
+                Some(row) if line.first().is_none_or(|cell| cell.is_empty()) => row.lines.push(line),
+                _ => {
+                    let id = rows.len();
+                    by_name.entry(line[0].clone()).or_insert(id);
+
+                    rows.push(Row { id, line: line_number, columns: Arc::clone(&columns), lines: vec![line] });
+                }
+            }
+        }
+
+        Ok(Self { name, columns, rows, by_name })
+    }
+
+    pub fn columns(&self) -> &Columns {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Looks a row up by the value of its first column.
+    pub fn find(&self, name: &str) -> Option<&Row> {
+        self.by_name.get(name).map(|&id| &self.rows[id])
+    }
+}
@@ -0,0 +1,25 @@
+use crate::csv_logic::Row;
+
+/// A row of player_thumbnails.csv: a profile icon and what unlocks it.
+pub struct ThumbnailRow {
+    pub id: usize,
+    pub name: String,
+    pub required_exp_level: i32,
+    /// Highest trophies the player needs.
+    pub required_total_trophies: i32,
+    /// Name of the brawler in characters.csv the player needs, empty for
+    /// none.
+    pub required_hero: String,
+}
+
+impl From<&Row> for ThumbnailRow {
+    fn from(row: &Row) -> Self {
+        Self {
+            id: row.id,
+            name: row.name().to_string(),
+            required_exp_level: row.int("RequiredExpLevel"),
+            required_total_trophies: row.int("RequiredTotalTrophies"),
+            required_hero: row.get("RequiredHero").to_string(),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::csv_logic::Row;
+
+/// A row of tiles.csv: what a tile code in maps.csv stands for.
+pub struct TileRow {
+    pub id: usize,
+    pub name: String,
+    pub code: char,
+    pub blocks_movement: bool,
+    pub blocks_projectiles: bool,
+    pub is_destructible: bool,
+    pub hides_hero: bool,
+    /// Seconds until a destroyed tile grows back, 0 if it never does.
+    pub respawn_seconds: i32,
+}
+
+impl From<&Row> for TileRow {
+    fn from(row: &Row) -> Self {
+        Self {
+            id: row.id,
+            name: row.name().to_string(),
+            code: row.get("TileCode").chars().next().unwrap_or(' '),
+            blocks_movement: row.bool("BlocksMovement"),
+            blocks_projectiles: row.bool("BlocksProjectiles"),
+            is_destructible: row.bool("IsDestructible"),
+            hides_hero: row.bool("HidesHero"),
+            respawn_seconds: row.int("RespawnSeconds"),
+        }
+    }
+}
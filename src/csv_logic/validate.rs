@@ -0,0 +1,323 @@
+use std::collections::HashSet;
+
+use crate::csv_logic::{ColumnType, GameData, Marker, Table, BOX_COST_RESOURCES, HOME_CONF_NUMBERS_LEN, REQUIRED_GLOBALS, REQUIRED_TRACKS};
+
+/// Columns the server reads, by table.
+const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    ("cards", &["Name", "Target", "Type", "Skill", "Value", "Value2", "NumCards", "Rarity"]),
+    ("characters", &["Name", "Disabled", "WeaponSkill", "UltimateSkill", "Type", "DefaultSkin"]),
+    ("skins", &["Name", "Character", "CostGems"]),
+    ("skills", &["Name", "Projectile"]),
+    ("projectiles", &["Name"]),
+    ("locations", &["Name", "TID", "GameMode", "AllowedMaps"]),
+    ("maps", &["CodeName", "Group", "Data"]),
+    ("tiles", &["Name", "TileCode"]),
+    ("globals", &["Name", "NumberValue", "BooleanValue", "TextValue"]),
+    ("boxes", &["Name", "Type", "CostResource", "Cost", "Rolls", "GoldMin", "GoldMax", "PowerPointsMin", "PowerPointsMax", "Rarities", "BrawlerChance", "PityStep"]),
+    ("player_thumbnails", &["Name", "RequiredExpLevel", "RequiredTotalTrophies", "RequiredHero"]),
+    ("milestones", &["Name", "Type", "Thresholds", "ExtraStep", "StepIncrease", "Steps", "RewardType", "RewardAmount", "RewardResource"]),
+];
+
+/// A column naming a row of another table.
+struct Reference {
+    table: &'static str,
+    column: &'static str,
+    target: &'static str,
+    /// Column of the target the value has to appear in.
+    target_column: &'static str,
+}
+
+const fn reference(table: &'static str, column: &'static str, target: &'static str) -> Reference {
+    Reference { table, column, target, target_column: "Name" }
+}
+
+const REFERENCES: &[Reference] = &[
+    reference("cards", "Target", "characters"),
+    reference("cards", "RequiresCard", "cards"),
+    reference("cards", "Skill", "skills"),
+    reference("skins", "Character", "characters"),
+    reference("skins", "PetSkin", "skins"),
+    reference("characters", "WeaponSkill", "skills"),
+    reference("characters", "UltimateSkill", "skills"),
+    reference("characters", "Pet", "characters"),
+    reference("characters", "AutoAttackProjectile", "projectiles"),
+    reference("characters", "DefaultSkin", "skins"),
+    reference("characters", "AreaEffect", "area_effects"),
+    reference("characters", "DeathAreaEffect", "area_effects"),
+    reference("skills", "Projectile", "projectiles"),
+    reference("skills", "SummonedCharacter", "characters"),
+    reference("skills", "AreaEffectObject", "area_effects"),
+    reference("skills", "AreaEffectObject2", "area_effects"),
+    reference("skills", "SpawnedItem", "items"),
+    reference("projectiles", "SpawnAreaEffectObject", "area_effects"),
+    reference("projectiles", "SpawnAreaEffectObject2", "area_effects"),
+    reference("projectiles", "SpawnCharacter", "characters"),
+    reference("projectiles", "SpawnItem", "items"),
+    reference("projectiles", "ChainBullet", "projectiles"),
+    reference("area_effects", "BulletExplosionBullet", "projectiles"),
+    reference("items", "TriggerAreaEffect", "area_effects"),
+    reference("player_thumbnails", "RequiredHero", "characters"),
+    reference("milestones", "RewardResource", "resources"),
+    Reference { table: "locations", column: "AllowedMaps", target: "maps", target_column: "Group" },
+];
+
+/// Checks required columns, declared column types and references between
+/// tables. Every problem names the file and line it was found on.
+pub fn check(data: &GameData) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (name, columns) in REQUIRED_COLUMNS {
+        let Some(table) = data.table(name) else {
+            problems.push(format!("{}.csv is missing", name));
+            continue;
+        };
+
+        if table.rows().is_empty() {
+            problems.push(format!("{}.csv has no rows", name));
+        }
+
+        for column in columns.iter().filter(|column| table.columns().position(column).is_none()) {
+            problems.push(format!("{}.csv has no {} column", name, column));
+        }
+    }
+
+    let mut tables: Vec<&Table> = data.tables().collect();
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for table in tables {
+        check_types(table, &mut problems);
+    }
+
+    for reference in REFERENCES {
+        check_reference(data, reference, &mut problems);
+    }
+
+    check_globals(data, &mut problems);
+    check_milestones(data, &mut problems);
+    check_boxes(data, &mut problems);
+    check_maps(data, &mut problems);
+
+    problems
+}
+
+fn check_types(table: &Table, problems: &mut Vec<String>) {
+    for column in table.columns().names() {
+        let column_type = table.columns().column_type(column).unwrap_or(ColumnType::String);
+
+        let valid: fn(&str) -> bool = match column_type {
+            ColumnType::String => continue,
+            ColumnType::Int => |value| value.parse::<i32>().is_ok(),
+            ColumnType::Boolean => |value| value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false"),
+        };
+
+        for row in table.rows() {
+            for (line, value) in row.cells(column).filter(|(_, value)| !value.is_empty() && !valid(value)) {
+                problems.push(format!("{}.csv line {}: {} is {:?}, expected {:?}", table.name, line, column, value, column_type));
+            }
+        }
+    }
+}
+
+fn check_reference(data: &GameData, reference: &Reference, problems: &mut Vec<String>) {
+    // missing tables and columns are already reported as required ones
+    let (Some(table), Some(target)) = (data.table(reference.table), data.table(reference.target)) else {
+        return;
+    };
+
+    let names: HashSet<&str> = target.rows().iter()
+        .flat_map(|row| row.array(reference.target_column))
+        .collect();
+
+    for row in table.rows() {
+        for (line, value) in row.cells(reference.column).filter(|(_, value)| !value.is_empty()) {
+            if !names.contains(value) {
+                problems.push(format!(
+                    "{}.csv line {} ({}): {} {:?} is not a {} in {}.csv",
+                    table.name, line, row.name(), reference.column, value, reference.target_column, reference.target
+                ));
+            }
+        }
+    }
+}
+
+fn check_globals(data: &GameData, problems: &mut Vec<String>) {
+    let Some(globals) = data.table("globals") else {
+        return;
+    };
+
+    for name in REQUIRED_GLOBALS.iter().filter(|name| globals.find(name).is_none()) {
+        problems.push(format!("globals.csv has no {} row", name));
+    }
+
+    let numbers = data.globals.home_conf_numbers.len();
+
+    if globals.find("HOME_CONF_NUMBERS").is_some() && numbers != HOME_CONF_NUMBERS_LEN {
+        problems.push(format!("globals.csv: HOME_CONF_NUMBERS has {} values, expected {}", numbers, HOME_CONF_NUMBERS_LEN));
+    }
+}
+
+fn check_maps(data: &GameData, problems: &mut Vec<String>) {
+    let Some(maps) = data.table("maps") else {
+        return;
+    };
+
+    let codes: HashSet<char> = data.tiles.iter().map(|tile| tile.code).collect();
+
+    for map in &data.maps {
+        for (line, width) in map.uneven_rows() {
+            problems.push(format!("maps.csv line {} ({}): row is {} tiles wide, expected {}", line, map.name, width, map.width));
+        }
+
+        if map.spawns(1).is_empty() {
+            problems.push(format!("maps.csv line {} ({}): map has no spawn", map.line, map.name));
+        }
+    }
+
+    for row in maps.rows() {
+        for code in row.get("Data").chars().filter(|code| !codes.contains(code) && Marker::from_code(*code).is_none()) {
+            problems.push(format!("maps.csv line {}: {:?} is not a TileCode in tiles.csv", row.line, code));
+        }
+    }
+}
+
+fn check_milestones(data: &GameData, problems: &mut Vec<String>) {
+    let Some(table) = data.table("milestones") else {
+        return;
+    };
+
+    for name in REQUIRED_TRACKS.iter().filter(|name| table.find(name).is_none()) {
+        problems.push(format!("milestones.csv has no {} row", name));
+    }
+
+    for track in &data.milestones {
+        for (index, threshold) in track.decreasing() {
+            problems.push(format!(
+                "milestones.csv ({}): threshold {} of step {} is not above the one before",
+                track.name, threshold, index
+            ));
+        }
+
+        for index in track.untyped_rewards() {
+            problems.push(format!("milestones.csv ({}): reward of step {} has no RewardType", track.name, index));
+        }
+    }
+}
+
+fn check_boxes(data: &GameData, problems: &mut Vec<String>) {
+    let rarities: HashSet<&str> = data.cards.iter().filter(|card| card.is_unlock()).map(|card| card.rarity.as_str()).collect();
+    let mut types = HashSet::new();
+
+    for row in &data.boxes {
+        if !types.insert(row.box_type) {
+            problems.push(format!("boxes.csv ({}): type {} is used by another box", row.name, row.box_type));
+        }
+
+        if !BOX_COST_RESOURCES.contains(&row.cost_resource.as_str()) {
+            problems.push(format!("boxes.csv ({}): cannot cost {:?}, expected one of {:?}", row.name, row.cost_resource, BOX_COST_RESOURCES));
+        }
+
+        if row.rolls < 1 {
+            problems.push(format!("boxes.csv ({}): needs at least 1 roll", row.name));
+        }
+
+        if row.gold.0 > row.gold.1 || row.power_points.0 > row.power_points.1 {
+            problems.push(format!("boxes.csv ({}): a minimum is above its maximum", row.name));
+        }
+
+        for drop in row.drops.iter().filter(|drop| !rarities.contains(drop.rarity.as_str())) {
+            problems.push(format!("boxes.csv ({}): no brawler has rarity {}", row.name, drop.rarity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_logic::DATA_DIR;
+    use std::path::PathBuf;
+
+    /// Loads a copy of the shipped data with `edits` applied, each replacing
+    /// the first match of a string in one file.
+    fn load_edited(name: &str, edits: &[(&str, &str, &str)]) -> GameData {
+        let dir = std::env::temp_dir().join(format!("deuce-validate-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for entry in std::fs::read_dir(DATA_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            std::fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+        }
+
+        for (file, from, to) in edits {
+            let path: PathBuf = dir.join(file);
+            let text = std::fs::read_to_string(&path).unwrap();
+
+            assert!(text.contains(from), "{} has no {:?}", file, from);
+            std::fs::write(&path, text.replacen(from, to, 1)).unwrap();
+        }
+
+        let data = GameData::load(dir.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        data
+    }
+
+    fn assert_problem(problems: &[String], expected: &str) {
+        assert!(problems.iter().any(|problem| problem.contains(expected)), "no {:?} in {:#?}", expected, problems);
+    }
+
+    #[test]
+    fn shipped_data_is_valid() {
+        assert_eq!(check(&load_edited("shipped", &[])), Vec::<String>::new());
+    }
+
+    #[test]
+    fn broken_references() {
+        let data = load_edited("references", &[
+            ("locations.csv", "\"Wanted_1\"", "\"Wanted_99\""),
+            ("skins.csv", "\"BanditGirlDefault\",\"ShotgunGirl\"", "\"BanditGirlDefault\",\"Nobody\""),
+        ]);
+        let problems = check(&data);
+
+        assert_problem(&problems, "locations.csv line 3 (Wanted1): AllowedMaps \"Wanted_99\" is not a Group in maps.csv");
+        assert_problem(&problems, "skins.csv line 3 (BanditGirlDefault): Character \"Nobody\" is not a Name in characters.csv");
+    }
+
+    #[test]
+    fn column_types() {
+        let data = load_edited("types", &[
+            ("locations.csv", "\"BountyHunter\",\"Wanted_1\",0,66", "\"BountyHunter\",\"Wanted_1\",dark,66"),
+        ]);
+
+        assert_problem(&check(&data), "locations.csv line 3: ShadowR is \"dark\", expected Int");
+    }
+
+    #[test]
+    fn missing_globals() {
+        let data = load_edited("globals", &[
+            ("globals.csv", "\"EXP_FOR_VICTORY\"", "\"EXP_FOR_WINNING\""),
+        ]);
+
+        assert_problem(&check(&data), "globals.csv has no EXP_FOR_VICTORY row");
+    }
+
+    #[test]
+    fn broken_boxes() {
+        let data = load_edited("boxes", &[
+            ("boxes.csv", "\"BrawlBox\",10,\"Tokens\",100,1", "\"BrawlBox\",10,\"Tickets\",lots,0"),
+        ]);
+        let problems = check(&data);
+
+        assert_problem(&problems, "boxes.csv line 3: Cost is \"lots\", expected Int");
+        assert_problem(&problems, "boxes.csv (BrawlBox): cannot cost \"Tickets\"");
+        assert_problem(&problems, "boxes.csv (BrawlBox): needs at least 1 roll");
+    }
+
+    #[test]
+    fn untyped_milestone_rewards() {
+        let data = load_edited("milestones", &[
+            ("milestones.csv", "\"TrophyRoad\",,0,500,0,20,1,20", "\"TrophyRoad\",,0,500,0,20,,20"),
+        ]);
+
+        assert_problem(&check(&data), "milestones.csv (TrophyRoad): reward of step 0 has no RewardType");
+    }
+}
@@ -0,0 +1,25 @@
+/// Class ids the client uses to refer to rows of the tables in `assets/csv_logic`.
+pub const RESOURCES: i32 = 5;
+pub const LOCATIONS: i32 = 15;
+pub const CHARACTERS: i32 = 16;
+pub const CARDS: i32 = 23;
+pub const PLAYER_THUMBNAILS: i32 = 28;
+pub const SKINS: i32 = 29;
+
+/// A reference to a single row of a data table (the client's "sc id").
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DataRef {
+    pub class_id: i32,
+    pub instance_id: i32,
+}
+
+impl DataRef {
+    pub fn new(class_id: i32, instance_id: i32) -> Self {
+        Self { class_id, instance_id }
+    }
+
+    /// The empty reference, encoded as a single zero.
+    pub fn null() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use log::*;
+
+use crate::database::*;
+use crate::logic_long::LogicLong;
+use crate::player::Player;
+
+/// Bumped whenever the layout of [`PlayerExport`] changes.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// A player's account as moved between servers.
+#[derive(Serialize, Deserialize)]
+pub struct PlayerExport {
+    pub version: u32,
+    /// Hash of the account token, so the player's client keeps working on
+    /// the server the account is moved to.
+    pub token: String,
+    pub player: PlayerInfo,
+    pub club: Option<ExportedClub>,
+}
+
+/// Club membership at the time of the export. The club itself is not moved.
+#[derive(Serialize, Deserialize)]
+pub struct ExportedClub {
+    pub id: i32,
+    pub name: String,
+    pub role: i32,
+}
+
+pub fn export_player(database: &dyn Storage, id: LogicLong) -> Result<PlayerExport, DatabaseError> {
+    let player = database.find_player_by_id(id)?.ok_or(DatabaseError::NotFound)?;
+    let token = database.find_token(id)?.ok_or(DatabaseError::NotFound)?;
+
+    let club = match player.club_id {
+        0 => None,
+        club_id => database.load_club(club_id as i32)?.map(|club| ExportedClub {
+            id: club.id,
+            name: club.name,
+            role: player.club_role as i32,
+        }),
+    };
+
+    Ok(PlayerExport { version: EXPORT_VERSION, token, player, club })
+}
+
+/// Creates the exported account under a newly allocated id and returns it.
+/// The club membership is only restored if this server has the same club.
+pub fn import_player(database: &dyn Storage, export: &PlayerExport) -> Result<LogicLong, DatabaseError> {
+    if export.version != EXPORT_VERSION {
+        return Err(DatabaseError::Incompatible(format!("export version {}, expected {}", export.version, EXPORT_VERSION)));
+    }
+
+    if database.token_exists(&export.token)? {
+        return Err(DatabaseError::AlreadyExists);
+    }
+
+    let mut player = Player::new();
+    player.token = Some(export.token.clone());
+
+    let id = database.create_player(&player)?;
+
+    // the backends have no transaction spanning these calls, so undo the
+    // account if the rest of the import fails
+    if let Err(e) = fill_imported(database, export, &mut player, id) {
+        if let Err(cleanup) = database.delete_player(id) {
+            error!("deuce: failed to remove half-imported player {}: {}", id, cleanup);
+        }
+
+        return Err(e);
+    }
+
+    Ok(id)
+}
+
+/// Writes the exported progress and club membership over the freshly
+/// created account `id`.
+fn fill_imported(database: &dyn Storage, export: &PlayerExport, player: &mut Player, id: LogicLong) -> Result<(), DatabaseError> {
+    let created = database.load_player(player)?;
+
+    player.load(&export.player).expect("deuce: token was set above");
+    player.id = id;
+    player.load_revision(created.revision);
+    player.mark_dirty();
+
+    database.save_player(player)?;
+
+    if let Some(club) = &export.club {
+        match database.load_club(club.id)? {
+            Some(existing) if existing.name == club.name => {
+                database.set_club_member(club.id, &ClubMember { id, role: club.role })?;
+            }
+            _ => warn!("deuce: club {} ({}) does not exist here, {} is imported without one", club.id, club.name, id),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exported(database: &MemoryStorage) -> PlayerExport {
+        let mut player = Player::new();
+        player.token = Some("token".to_string());
+
+        let id = database.create_player(&player).unwrap();
+        player.load(&database.load_player(&player).unwrap()).unwrap();
+        player.gold = 123;
+        player.mark_dirty();
+        database.save_player(&mut player).unwrap();
+
+        let club = Club {
+            id: 0,
+            name: "Club".to_string(),
+            description: String::new(),
+            badge: 0,
+            club_type: 1,
+            required_trophies: 0,
+            region: String::new(),
+            members: vec![ClubMember { id, role: 2 }],
+        };
+        database.add_club(&club).unwrap();
+
+        export_player(database, id).unwrap()
+    }
+
+    #[test]
+    fn import_moves_the_account() {
+        let source = MemoryStorage::new();
+        let export = exported(&source);
+
+        let target = MemoryStorage::new();
+        target.add_club(&Club { members: Vec::new(), ..source.load_club(1).unwrap().unwrap() }).unwrap();
+
+        let mut other = Player::new();
+        other.token = Some("other".to_string());
+        target.create_player(&other).unwrap();
+
+        let id = import_player(&target, &export).unwrap();
+        let info = target.find_player_by_id(id).unwrap().unwrap();
+
+        assert_ne!(id, export.player.id);
+        assert_eq!((info.gold, info.club_id, info.club_role), (123, 1, 2));
+        assert_eq!(target.find_token(id).unwrap().as_deref(), Some("token"));
+    }
+
+    #[test]
+    fn import_rejects_known_tokens_and_versions() {
+        let database = MemoryStorage::new();
+        let mut export = exported(&database);
+
+        assert!(matches!(import_player(&database, &export), Err(DatabaseError::AlreadyExists)));
+
+        export.version += 1;
+        assert!(matches!(import_player(&MemoryStorage::new(), &export), Err(DatabaseError::Incompatible(_))));
+    }
+}
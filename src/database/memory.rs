@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::database::*;
+use crate::logic_long::LogicLong;
+use crate::names;
+use crate::player::*;
+
+/// Keeps everything in process memory. Nothing survives a restart, which
+/// makes it a good fit for tests and throwaway servers.
+pub struct MemoryStorage {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    players: HashMap<String, PlayerInfo>,
+    next_low_id: u32,
+    clubs: BTreeMap<i32, Club>,
+    events: BTreeMap<i32, String>,
+    /// Claimed names by [`names::key`].
+    names: HashMap<String, LogicLong>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        let state = MemoryState {
+            next_low_id: 1,
+            ..MemoryState::default()
+        };
+
+        Self { state: Mutex::new(state) }
+    }
+}
+
+impl MemoryState {
+    fn with_club(&self, info: &PlayerInfo) -> PlayerInfo {
+        let mut info = info.clone();
+
+        for club in self.clubs.values() {
+            if let Some(member) = club.members.iter().find(|m| m.id == info.id) {
+                info.club_id = club.id as u32;
+                info.club_role = member.role as u32;
+            }
+        }
+
+        info
+    }
+
+    fn player_exists(&self, id: LogicLong) -> bool {
+        self.players.values().any(|info| info.id == id)
+    }
+
+    fn migrate(&mut self) -> Result<i32, DatabaseError> {
+        Ok(0)
+    }
+
+    fn create_player(&mut self, player: &Player) -> Result<LogicLong, DatabaseError> {
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+
+        if self.players.contains_key(token) {
+            return Err(DatabaseError::AlreadyExists);
+        }
+
+        let id = LogicLong::new(0, self.next_low_id);
+        self.next_low_id += 1;
+
+        let info = PlayerInfo { id, ..PlayerInfo::new_account(player) };
+        self.players.insert(token.to_string(), info);
+
+        Ok(id)
+    }
+
+    fn load_player(&mut self, player: &Player) -> Result<PlayerInfo, DatabaseError> {
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+
+        match self.players.get(token) {
+            Some(info) => Ok(self.with_club(info)),
+            None => Err(DatabaseError::NotFound),
+        }
+    }
+
+    fn find_player_by_id(&self, id: LogicLong) -> Result<Option<PlayerInfo>, DatabaseError> {
+        Ok(self.players.values().find(|info| info.id == id).map(|info| self.with_club(info)))
+    }
+
+    fn save_player(&mut self, player: &mut Player) -> Result<(), DatabaseError> {
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+        let stored = self.players.get_mut(token).ok_or(DatabaseError::NotFound)?;
+
+        if stored.revision != player.revision {
+            return Err(DatabaseError::Conflict);
+        }
+
+        *stored = PlayerInfo { revision: player.revision + 1, ..PlayerInfo::from(&*player) };
+        player.load_revision(stored.revision);
+
+        Ok(())
+    }
+
+    fn token_exists(&self, token: &str) -> Result<bool, DatabaseError> {
+        Ok(self.players.contains_key(token))
+    }
+
+    fn claim_name(&mut self, id: LogicLong, name: &str) -> Result<(), DatabaseError> {
+        if !self.player_exists(id) {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let key = names::key(name);
+
+        if self.names.get(&key).is_some_and(|&holder| holder != id) {
+            return Err(DatabaseError::AlreadyExists);
+        }
+
+        self.names.retain(|_, holder| *holder != id);
+        self.names.insert(key, id);
+
+        Ok(())
+    }
+
+    fn set_token(&mut self, id: LogicLong, token: &str) -> Result<(), DatabaseError> {
+        let old = self.players.iter()
+            .find(|(_, info)| info.id == id)
+            .map(|(old, _)| old.clone())
+            .ok_or(DatabaseError::NotFound)?;
+
+        let info = self.players.remove(&old).unwrap();
+        self.players.insert(token.to_string(), info);
+
+        Ok(())
+    }
+
+    fn find_token(&self, id: LogicLong) -> Result<Option<String>, DatabaseError> {
+        Ok(self.players.iter().find(|(_, info)| info.id == id).map(|(token, _)| token.clone()))
+    }
+
+    fn delete_player(&mut self, id: LogicLong) -> Result<(), DatabaseError> {
+        let token = self.find_token(id)?.ok_or(DatabaseError::NotFound)?;
+
+        self.players.remove(&token);
+        self.remove_club_member(id)?;
+        self.names.retain(|_, holder| *holder != id);
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn create_club(&mut self, club: &Club) -> Result<i32, DatabaseError> {
+        let id = self.clubs.keys().next_back().map_or(1, |id| id + 1);
+
+        // like the SQL backends, unknown players are skipped and members of
+        // another club are moved over
+        let members: Vec<ClubMember> = club.members.iter()
+            .filter(|member| self.player_exists(member.id))
+            .cloned()
+            .collect();
+
+        for member in members.iter() {
+            self.remove_club_member(member.id)?;
+        }
+
+        self.clubs.insert(id, Club { id, members, ..club.clone() });
+
+        Ok(id)
+    }
+
+    fn load_club(&self, id: i32) -> Result<Option<Club>, DatabaseError> {
+        Ok(self.clubs.get(&id).cloned())
+    }
+
+    fn set_club_member(&mut self, club_id: i32, member: &ClubMember) -> Result<(), DatabaseError> {
+        if !self.clubs.contains_key(&club_id) || !self.player_exists(member.id) {
+            return Err(DatabaseError::NotFound);
+        }
+
+        self.remove_club_member(member.id)?;
+        self.clubs.get_mut(&club_id).unwrap().members.push(member.clone());
+
+        Ok(())
+    }
+
+    fn remove_club_member(&mut self, id: LogicLong) -> Result<(), DatabaseError> {
+        for club in self.clubs.values_mut() {
+            club.members.retain(|m| m.id != id);
+        }
+
+        Ok(())
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, DatabaseError> {
+        Ok(self.events.iter().map(|(&slot, data)| EventRecord { slot, data: data.clone() }).collect())
+    }
+
+    fn save_event(&mut self, event: &EventRecord) -> Result<(), DatabaseError> {
+        self.events.insert(event.slot, event.data.clone());
+
+        Ok(())
+    }
+
+}
+
+#[cfg(test)]
+impl MemoryStorage {
+    /// Adds a club for tests, the server itself never creates one.
+    pub fn add_club(&self, club: &Club) -> Result<i32, DatabaseError> {
+        self.state.lock().unwrap().create_club(club)
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn migrate(&self) -> Result<i32, DatabaseError> {
+        self.state.lock().unwrap().migrate()
+    }
+
+    fn create_player(&self, player: &Player) -> Result<LogicLong, DatabaseError> {
+        self.state.lock().unwrap().create_player(player)
+    }
+
+    fn load_player(&self, player: &Player) -> Result<PlayerInfo, DatabaseError> {
+        self.state.lock().unwrap().load_player(player)
+    }
+
+    fn find_player_by_id(&self, id: LogicLong) -> Result<Option<PlayerInfo>, DatabaseError> {
+        self.state.lock().unwrap().find_player_by_id(id)
+    }
+
+    fn save_player(&self, player: &mut Player) -> Result<(), DatabaseError> {
+        self.state.lock().unwrap().save_player(player)
+    }
+
+    fn token_exists(&self, token: &str) -> Result<bool, DatabaseError> {
+        self.state.lock().unwrap().token_exists(token)
+    }
+
+    fn claim_name(&self, id: LogicLong, name: &str) -> Result<(), DatabaseError> {
+        self.state.lock().unwrap().claim_name(id, name)
+    }
+
+    fn set_token(&self, id: LogicLong, token: &str) -> Result<(), DatabaseError> {
+        self.state.lock().unwrap().set_token(id, token)
+    }
+
+    fn find_token(&self, id: LogicLong) -> Result<Option<String>, DatabaseError> {
+        self.state.lock().unwrap().find_token(id)
+    }
+
+    fn delete_player(&self, id: LogicLong) -> Result<(), DatabaseError> {
+        self.state.lock().unwrap().delete_player(id)
+    }
+
+    fn load_club(&self, id: i32) -> Result<Option<Club>, DatabaseError> {
+        self.state.lock().unwrap().load_club(id)
+    }
+
+    fn set_club_member(&self, club_id: i32, member: &ClubMember) -> Result<(), DatabaseError> {
+        self.state.lock().unwrap().set_club_member(club_id, member)
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, DatabaseError> {
+        self.state.lock().unwrap().load_events()
+    }
+
+    fn save_event(&self, event: &EventRecord) -> Result<(), DatabaseError> {
+        self.state.lock().unwrap().save_event(event)
+    }
+
+    fn snapshot(&self) -> Result<Snapshot, DatabaseError> {
+        Err(DatabaseError::Unsupported("snapshots of the memory backend"))
+    }
+
+    fn restore(&self, _snapshot: &Snapshot, _replace: bool) -> Result<(), DatabaseError> {
+        Err(DatabaseError::Unsupported("snapshots of the memory backend"))
+    }
+}
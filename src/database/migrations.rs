@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+use log::*;
+use postgres::{Client, GenericClient, Transaction};
+use serde::Deserialize;
+
+use crate::database::DatabaseError;
+use crate::names;
+
+/// A forward-only schema change. Migrations run in order inside their own
+/// transaction and are recorded in `schema_version` once applied.
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub up: fn(&mut Transaction) -> Result<(), DatabaseError>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "legacy json tables", up: legacy_tables },
+    Migration { version: 2, name: "typed schema", up: typed_schema },
+    Migration { version: 3, name: "migrate json players", up: migrate_json_players },
+    Migration { version: 4, name: "player revisions", up: player_revisions },
+    Migration { version: 5, name: "player id sequence", up: player_id_sequence },
+    Migration { version: 6, name: "bans", up: bans },
+    Migration { version: 7, name: "hashed tokens", up: hashed_tokens },
+    Migration { version: 8, name: "claimed ranks", up: claimed_ranks },
+    Migration { version: 9, name: "name changes", up: name_changes },
+    Migration { version: 10, name: "box pity", up: box_pity },
+    Migration { version: 11, name: "player names", up: player_names },
+];
+
+pub fn current_version(conn: &mut impl GenericClient) -> Result<i32, DatabaseError> {
+    let row = conn.query_one("SELECT COALESCE(MAX(version), 0) AS version FROM schema_version", &[])?;
+
+    Ok(row.get("version"))
+}
+
+/// Applies every migration newer than the recorded schema version.
+pub fn run(conn: &mut Client) -> Result<i32, DatabaseError> {
+    conn.batch_execute(
+        r#"CREATE TABLE IF NOT EXISTS schema_version (
+            version INT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );"#
+    )?;
+
+    let current = current_version(conn)?;
+    let mut version = current;
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        info!("deuce: applying migration {} ({})", migration.version, migration.name);
+
+        let mut tx = conn.transaction()?;
+
+        (migration.up)(&mut tx)?;
+
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES ($1, $2)",
+            &[&migration.version, &migration.name]
+        )?;
+
+        tx.commit()?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+/// The key/json tables the server used to create at startup.
+fn legacy_tables(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute(
+        r#"CREATE TABLE IF NOT EXISTS players (token TEXT PRIMARY KEY, data TEXT);
+        CREATE TABLE IF NOT EXISTS rooms (id INT PRIMARY KEY, data TEXT);
+        CREATE TABLE IF NOT EXISTS clubs (id INT PRIMARY KEY, data TEXT);
+        CREATE TABLE IF NOT EXISTS club_chats (id INT PRIMARY KEY, data TEXT);
+        CREATE TABLE IF NOT EXISTS room_chats (id INT PRIMARY KEY, data TEXT);
+        CREATE TABLE IF NOT EXISTS events (state INT PRIMARY KEY, data TEXT);"#
+    )?;
+
+    Ok(())
+}
+
+fn typed_schema(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    // rooms, clubs and both chat tables were created but never written to
+    tx.batch_execute(
+        r#"ALTER TABLE players RENAME TO players_json;
+        DROP TABLE rooms, clubs, club_chats, room_chats;
+
+        CREATE TABLE players (
+            id SERIAL PRIMARY KEY,
+            token TEXT NOT NULL UNIQUE,
+            high_id INT NOT NULL DEFAULT 0,
+            low_id INT NOT NULL,
+            name TEXT NOT NULL,
+            region TEXT NOT NULL DEFAULT '',
+            experience INT NOT NULL DEFAULT 0,
+            trophies INT NOT NULL DEFAULT 0,
+            highest_trophies INT NOT NULL DEFAULT 0,
+            solo_wins INT NOT NULL DEFAULT 0,
+            duo_wins INT NOT NULL DEFAULT 0,
+            three_x_three_wins INT NOT NULL DEFAULT 0,
+            profile_icon INT NOT NULL DEFAULT 0,
+            room_id INT NOT NULL DEFAULT 0,
+            control_mode INT NOT NULL DEFAULT 0,
+            has_battle_hints BOOLEAN NOT NULL DEFAULT false,
+            coins_reward INT NOT NULL DEFAULT 0,
+            event_count INT NOT NULL DEFAULT 4,
+            player_status INT NOT NULL DEFAULT 0,
+            last_connection_time INT NOT NULL DEFAULT 0
+        );
+        CREATE INDEX players_logic_id_idx ON players (high_id, low_id);
+
+        CREATE TABLE brawlers (
+            player_id INT NOT NULL REFERENCES players (id) ON DELETE CASCADE,
+            brawler_id INT NOT NULL,
+            selected_skin INT NOT NULL DEFAULT 0,
+            trophies INT NOT NULL DEFAULT 0,
+            highest_trophies INT NOT NULL DEFAULT 0,
+            level INT NOT NULL DEFAULT 0,
+            power_points INT NOT NULL DEFAULT 0,
+            state INT NOT NULL DEFAULT 0,
+            star_power INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, brawler_id)
+        );
+
+        CREATE TABLE brawler_cards (
+            player_id INT NOT NULL,
+            brawler_id INT NOT NULL,
+            card_id INT NOT NULL,
+            amount INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, brawler_id, card_id),
+            FOREIGN KEY (player_id, brawler_id) REFERENCES brawlers (player_id, brawler_id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE brawler_skins (
+            player_id INT NOT NULL,
+            brawler_id INT NOT NULL,
+            skin_id INT NOT NULL,
+            PRIMARY KEY (player_id, brawler_id, skin_id),
+            FOREIGN KEY (player_id, brawler_id) REFERENCES brawlers (player_id, brawler_id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE resources (
+            player_id INT NOT NULL REFERENCES players (id) ON DELETE CASCADE,
+            resource TEXT NOT NULL,
+            amount INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, resource)
+        );
+
+        CREATE TABLE clubs (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            badge INT NOT NULL DEFAULT 0,
+            club_type INT NOT NULL DEFAULT 0,
+            required_trophies INT NOT NULL DEFAULT 0,
+            region TEXT NOT NULL DEFAULT ''
+        );
+
+        CREATE TABLE club_members (
+            club_id INT NOT NULL REFERENCES clubs (id) ON DELETE CASCADE,
+            player_id INT NOT NULL UNIQUE REFERENCES players (id) ON DELETE CASCADE,
+            role INT NOT NULL DEFAULT 1,
+            PRIMARY KEY (club_id, player_id)
+        );
+
+        CREATE TABLE messages (
+            id BIGSERIAL PRIMARY KEY,
+            channel TEXT NOT NULL,
+            channel_id INT NOT NULL,
+            sender_id INT REFERENCES players (id) ON DELETE SET NULL,
+            kind INT NOT NULL DEFAULT 2,
+            text TEXT NOT NULL DEFAULT '',
+            sent_at BIGINT NOT NULL
+        );
+        CREATE INDEX messages_channel_idx ON messages (channel, channel_id, id);"#
+    )?;
+
+    Ok(())
+}
+
+/// A player as the server serialized it into `players.data` before the
+/// typed schema. Kept apart from `PlayerInfo`, which follows the current
+/// schema.
+#[derive(Deserialize)]
+struct JsonPlayer {
+    name: String,
+    low_id: u32,
+    player_experience: u32,
+    solo_wins: u32,
+    duo_wins: u32,
+    three_x_three_wins: u32,
+    gems: u32,
+    gold: u32,
+    elixir: u32,
+    chips: u32,
+    coins_doubler: u32,
+    coins_booster: u32,
+    trophies: u32,
+    highest_trophies: u32,
+    profile_icon: u32,
+    room_id: u32,
+    last_connection_time: u32,
+    player_status: u32,
+    region: String,
+    control_mode: u32,
+    has_battle_hints: bool,
+    unlocked_brawlers: HashMap<i32, JsonBrawler>,
+    coins_reward: i32,
+    event_count: i32,
+}
+
+#[derive(Deserialize)]
+struct JsonBrawler {
+    cards: HashMap<i32, i32>,
+    skins: Vec<i32>,
+    selected: i32,
+    trophies: i32,
+    highest_trophies: i32,
+    level: i32,
+    power_points: i32,
+    state: i32,
+    star_power: i32,
+}
+
+/// Moves every serialized player from the old key/json table into the
+/// typed tables as migration 2 created them. Later migrations change those
+/// tables, so this must not go through the live player queries.
+fn migrate_json_players(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    let rows = tx.query("SELECT token, data FROM players_json WHERE data IS NOT NULL", &[])?;
+    let count = rows.len();
+
+    for row in rows {
+        let token: String = row.get("token");
+        let data: String = row.get("data");
+
+        let player: JsonPlayer = serde_json::from_str(&data)?;
+        insert_json_player(tx, &token, &player)?;
+    }
+
+    tx.batch_execute("DROP TABLE players_json;")?;
+
+    info!("deuce: migrated {} json players", count);
+
+    Ok(())
+}
+
+fn insert_json_player(tx: &mut Transaction, token: &str, player: &JsonPlayer) -> Result<(), DatabaseError> {
+    let row = tx.query_one(
+        r#"INSERT INTO players (token, high_id, low_id, name, region, experience,
+            trophies, highest_trophies, solo_wins, duo_wins, three_x_three_wins,
+            profile_icon, room_id, control_mode, has_battle_hints, coins_reward,
+            event_count, player_status, last_connection_time)
+            VALUES ($1, 0, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            RETURNING id"#,
+        &[
+            &token,
+            &(player.low_id as i32),
+            &player.name,
+            &player.region,
+            &(player.player_experience as i32),
+            &(player.trophies as i32),
+            &(player.highest_trophies as i32),
+            &(player.solo_wins as i32),
+            &(player.duo_wins as i32),
+            &(player.three_x_three_wins as i32),
+            &(player.profile_icon as i32),
+            &(player.room_id as i32),
+            &(player.control_mode as i32),
+            &player.has_battle_hints,
+            &player.coins_reward,
+            &player.event_count,
+            &(player.player_status as i32),
+            &(player.last_connection_time as i32),
+        ]
+    )?;
+
+    let id: i32 = row.get("id");
+
+    for (&brawler_id, brawler) in player.unlocked_brawlers.iter() {
+        tx.execute(
+            r#"INSERT INTO brawlers (player_id, brawler_id, selected_skin, trophies,
+                highest_trophies, level, power_points, state, star_power)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#,
+            &[
+                &id,
+                &brawler_id,
+                &brawler.selected,
+                &brawler.trophies,
+                &brawler.highest_trophies,
+                &brawler.level,
+                &brawler.power_points,
+                &brawler.state,
+                &brawler.star_power,
+            ]
+        )?;
+
+        for (&card_id, &amount) in brawler.cards.iter() {
+            tx.execute(
+                "INSERT INTO brawler_cards (player_id, brawler_id, card_id, amount) VALUES ($1, $2, $3, $4)",
+                &[&id, &brawler_id, &card_id, &amount]
+            )?;
+        }
+
+        for &skin_id in brawler.skins.iter() {
+            tx.execute(
+                "INSERT INTO brawler_skins (player_id, brawler_id, skin_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                &[&id, &brawler_id, &skin_id]
+            )?;
+        }
+    }
+
+    let resources = [
+        ("gems", player.gems),
+        ("gold", player.gold),
+        ("elixir", player.elixir),
+        ("chips", player.chips),
+        ("coins_doubler", player.coins_doubler),
+        ("coins_booster", player.coins_booster),
+    ];
+
+    for (name, amount) in resources {
+        tx.execute(
+            "INSERT INTO resources (player_id, resource, amount) VALUES ($1, $2, $3)",
+            &[&id, &name, &(amount as i32)]
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Adds the revision counter `save_player` uses to detect lost updates.
+fn player_revisions(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute("ALTER TABLE players ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;")?;
+
+    Ok(())
+}
+
+/// Moves low id allocation into a sequence and makes ids unique. Accounts
+/// that were handed a duplicate id by the old `COUNT(*) + 1` allocator get
+/// a fresh one, the oldest account keeps its id.
+fn player_id_sequence(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute(
+        r#"CREATE SEQUENCE player_low_id_seq OWNED BY players.low_id;
+        SELECT setval('player_low_id_seq', COALESCE(MAX(low_id), 0) + 1, false) FROM players;
+
+        UPDATE players SET low_id = nextval('player_low_id_seq')
+            WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, ROW_NUMBER() OVER (PARTITION BY high_id, low_id ORDER BY id) AS n
+                    FROM players
+                ) duplicates WHERE n > 1
+            );
+
+        ALTER TABLE players ALTER COLUMN low_id SET DEFAULT nextval('player_low_id_seq');
+        DROP INDEX players_logic_id_idx;
+        CREATE UNIQUE INDEX players_logic_id_key ON players (high_id, low_id);"#
+    )?;
+
+    Ok(())
+}
+
+fn bans(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute(
+        r#"CREATE TABLE bans (
+            player_id INT PRIMARY KEY REFERENCES players (id) ON DELETE CASCADE,
+            reason TEXT NOT NULL DEFAULT '',
+            banned_at BIGINT NOT NULL,
+            expires_at BIGINT
+        );"#
+    )?;
+
+    Ok(())
+}
+
+/// Tokens used to be stored as sent by the client. Hash them in place so
+/// existing accounts can still log in with the token they already have.
+fn hashed_tokens(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute("UPDATE players SET token = encode(sha256(convert_to(token, 'UTF8')), 'hex');")?;
+
+    Ok(())
+}
+
+/// Brawlers start with no rank rewards claimed, so existing ones get theirs
+/// on their next login.
+fn claimed_ranks(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute("ALTER TABLE brawlers ADD COLUMN claimed_rank INT NOT NULL DEFAULT 1;")?;
+
+    Ok(())
+}
+
+fn name_changes(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute("ALTER TABLE players ADD COLUMN name_changes INT NOT NULL DEFAULT 0;")?;
+
+    Ok(())
+}
+
+fn box_pity(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute(
+        r#"CREATE TABLE box_pity (
+            player_id INT NOT NULL REFERENCES players (id) ON DELETE CASCADE,
+            rarity TEXT NOT NULL,
+            boxes INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, rarity)
+        );"#
+    )?;
+
+    Ok(())
+}
+
+/// Reserves names so two players cannot pick the same one at once. Players
+/// who already share a name keep it, the one who had it first holds it.
+/// Reserves every current name for its oldest holder. The keys come from
+/// [`names::key`] rather than `lower()`, which folds case differently.
+fn player_names(tx: &mut Transaction) -> Result<(), DatabaseError> {
+    tx.batch_execute(
+        r#"CREATE TABLE player_names (
+            name_key TEXT PRIMARY KEY,
+            player_id INT NOT NULL UNIQUE REFERENCES players (id) ON DELETE CASCADE
+        );"#
+    )?;
+
+    let default_key = names::key(names::DEFAULT_NAME);
+
+    for row in tx.query("SELECT id, name FROM players ORDER BY id", &[])? {
+        let key = names::key(row.get("name"));
+
+        if key == default_key {
+            continue;
+        }
+
+        tx.execute(
+            "INSERT INTO player_names (name_key, player_id) VALUES ($1, $2) ON CONFLICT (name_key) DO NOTHING",
+            &[&key, &row.get::<_, i32>("id")]
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::players;
+    use crate::token;
+
+    /// Connects to the Postgres server named by `DEUCE_TEST_POSTGRES` with a
+    /// fresh, empty schema on the search path. Tests that need one are
+    /// skipped when it is not set.
+    fn test_client(schema: &str) -> Option<Client> {
+        let url = std::env::var("DEUCE_TEST_POSTGRES").ok()?;
+        let mut client = Client::connect(&url, postgres::NoTls).unwrap();
+
+        client.batch_execute(&format!("DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0}; SET search_path TO {0};", schema)).unwrap();
+
+        Some(client)
+    }
+
+    #[test]
+    fn migrations_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i32 + 1, "{}", migration.name);
+        }
+    }
+
+    #[test]
+    fn json_players_survive_every_migration() {
+        let Some(mut client) = test_client("deuce_test_json_players") else {
+            eprintln!("DEUCE_TEST_POSTGRES is not set, skipping");
+            return;
+        };
+
+        // what the server left behind before it had migrations
+        let json = r#"{"name": "ÉMILE", "low_id": 7, "club_id": 0, "club_role": 0,
+            "player_experience": 120, "solo_wins": 1, "duo_wins": 2, "three_x_three_wins": 3,
+            "gems": 5, "gold": 92, "elixir": 0, "chips": 0, "coins_doubler": 0, "coins_booster": 0,
+            "trophies": 12, "highest_trophies": 30, "profile_icon": 0, "room_id": 0,
+            "last_connection_time": 0, "player_status": 3, "region": "CAT", "control_mode": 0,
+            "has_battle_hints": false, "coins_reward": 0, "event_count": 4,
+            "unlocked_brawlers": {"0": {"cards": {"0": 1}, "skins": [0], "selected": 0, "trophies": 12,
+                "highest_trophies": 30, "level": 0, "power_points": 0, "state": 2, "star_power": 0}}}"#;
+
+        client.batch_execute("CREATE TABLE players (token TEXT PRIMARY KEY, data TEXT);").unwrap();
+        client.execute("INSERT INTO players (token, data) VALUES ('legacy', $1)", &[&json]).unwrap();
+
+        assert_eq!(run(&mut client).unwrap(), MIGRATIONS.len() as i32);
+        assert_eq!(run(&mut client).unwrap(), MIGRATIONS.len() as i32);
+
+        let info = players::load_by_token(&mut client, &token::hash("legacy")).unwrap().unwrap();
+
+        assert_eq!((info.name.as_str(), info.id.low_id, info.region.as_str()), ("ÉMILE", 7, "CAT"));
+        assert_eq!((info.player_experience, info.trophies, info.highest_trophies), (120, 12, 30));
+        assert_eq!((info.gems, info.gold, info.tokens, info.name_changes), (5, 92, 0, 0));
+        assert!(info.box_pity.is_empty());
+
+        let brawler = &info.unlocked_brawlers[&0];
+        assert_eq!((brawler.trophies, brawler.state, brawler.skins.as_slice()), (12, 2, [0].as_slice()));
+        assert_eq!(brawler.cards.get(&0), Some(&1));
+        // brawlers from before ranks had claimed nothing past the first one
+        assert_eq!(brawler.claimed_rank, 1);
+
+        let key: String = client.query_one("SELECT name_key FROM player_names", &[]).unwrap().get(0);
+        assert_eq!(key, names::key("ÉMILE"));
+
+        client.batch_execute("DROP SCHEMA deuce_test_json_players CASCADE;").unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+mod migrations;
+mod players;
+mod models;
+mod pg;
+mod sqlite;
+mod memory;
+mod export;
+mod snapshot;
+
+use crate::player::*;
+use crate::logic_long::LogicLong;
+use crate::settings::Settings;
+use std::fmt;
+use std::sync::Arc;
+
+pub use models::*;
+pub use pg::*;
+pub use sqlite::*;
+pub use memory::*;
+pub use export::*;
+pub use snapshot::*;
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Postgres(postgres::Error),
+    Sqlite(rusqlite::Error),
+    Pool(r2d2::Error),
+    Tls(native_tls::Error),
+    Json(serde_json::Error),
+    NotFound,
+    AlreadyExists,
+    Conflict,
+    /// Data written by another version of the server.
+    Incompatible(String),
+    /// The backend cannot do this.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Postgres(e) => write!(f, "postgres error: {}", e),
+            DatabaseError::Sqlite(e) => write!(f, "sqlite error: {}", e),
+            DatabaseError::Pool(e) => write!(f, "connection pool error: {}", e),
+            DatabaseError::Tls(e) => write!(f, "tls error: {}", e),
+            DatabaseError::Json(e) => write!(f, "json error: {}", e),
+            DatabaseError::NotFound => write!(f, "row not found"),
+            DatabaseError::AlreadyExists => write!(f, "row already exists"),
+            DatabaseError::Conflict => write!(f, "row was changed by another session"),
+            DatabaseError::Incompatible(what) => write!(f, "incompatible data: {}", what),
+            DatabaseError::Unsupported(what) => write!(f, "not supported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<native_tls::Error> for DatabaseError {
+    fn from(e: native_tls::Error) -> Self {
+        DatabaseError::Tls(e)
+    }
+}
+
+impl From<postgres::Error> for DatabaseError {
+    fn from(err: postgres::Error) -> Self {
+        DatabaseError::Postgres(err)
+    }
+}
+
+impl From<rusqlite::Error> for DatabaseError {
+    fn from(err: rusqlite::Error) -> Self {
+        DatabaseError::Sqlite(err)
+    }
+}
+
+impl From<r2d2::Error> for DatabaseError {
+    fn from(err: r2d2::Error) -> Self {
+        DatabaseError::Pool(err)
+    }
+}
+
+impl From<serde_json::Error> for DatabaseError {
+    fn from(err: serde_json::Error) -> Self {
+        DatabaseError::Json(err)
+    }
+}
+
+/// Everything the server persists. Implemented by the Postgres backend used
+/// in production, an embedded SQLite backend for small deployments and an
+/// in-memory backend for tests.
+pub trait Storage: Send + Sync {
+    /// Brings the schema up to date, returning the resulting schema version.
+    fn migrate(&self) -> Result<i32, DatabaseError>;
+
+    /// Creates an account for the player's token and returns the id it was
+    /// given.
+    fn create_player(&self, player: &Player) -> Result<LogicLong, DatabaseError>;
+    fn load_player(&self, player: &Player) -> Result<PlayerInfo, DatabaseError>;
+    fn find_player_by_id(&self, id: LogicLong) -> Result<Option<PlayerInfo>, DatabaseError>;
+
+    /// Writes the player back to storage.
+    ///
+    /// The write only succeeds if the stored revision still matches the one
+    /// the player was loaded at, so a stale session cannot overwrite newer
+    /// progress. On success the player's revision is bumped and it is no
+    /// longer dirty.
+    fn save_player(&self, player: &mut Player) -> Result<(), DatabaseError>;
+    /// Tokens are only ever passed in hashed, see [`crate::token::hash`].
+    fn token_exists(&self, token: &str) -> Result<bool, DatabaseError>;
+    /// Replaces a player's token hash, logging out whoever held the old one.
+    fn set_token(&self, id: LogicLong, token: &str) -> Result<(), DatabaseError>;
+    /// Returns the stored token hash of a player.
+    fn find_token(&self, id: LogicLong) -> Result<Option<String>, DatabaseError>;
+    /// Reserves `name` for a player, releasing the one they held before.
+    /// Names are told apart by [`crate::names::key`]; if another player
+    /// holds the name this fails with [`DatabaseError::AlreadyExists`].
+    fn claim_name(&self, id: LogicLong, name: &str) -> Result<(), DatabaseError>;
+    /// Deletes an account for good, together with its chat messages, club
+    /// membership and ban.
+    fn delete_player(&self, id: LogicLong) -> Result<(), DatabaseError>;
+
+    /// Looks a player up by the `#TAG` shown in their profile.
+    fn find_player_by_tag(&self, tag: &str) -> Result<Option<PlayerInfo>, DatabaseError> {
+        match LogicLong::from_tag(tag) {
+            Some(id) => self.find_player_by_id(id),
+            None => Ok(None),
+        }
+    }
+
+    fn load_club(&self, id: i32) -> Result<Option<Club>, DatabaseError>;
+    /// Adds a player to a club, or moves them if they are in another one.
+    fn set_club_member(&self, club_id: i32, member: &ClubMember) -> Result<(), DatabaseError>;
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, DatabaseError>;
+    fn save_event(&self, event: &EventRecord) -> Result<(), DatabaseError>;
+
+    /// Copies every table inside one transaction.
+    fn snapshot(&self) -> Result<Snapshot, DatabaseError>;
+    /// Loads a snapshot taken by [`Storage::snapshot`]. The database has to
+    /// be empty unless `replace` is set, in which case everything in it is
+    /// deleted first.
+    fn restore(&self, snapshot: &Snapshot, replace: bool) -> Result<(), DatabaseError>;
+}
+
+/// Opens the backend named by the url's scheme: `postgres://`,
+/// `sqlite://<path>` or `memory://`.
+pub fn open(settings: &Settings) -> Result<Arc<dyn Storage>, DatabaseError> {
+    let url = settings.database.as_str();
+
+    if let Some(path) = url.strip_prefix("sqlite://") {
+        return Ok(Arc::new(SqliteStorage::open(path)?));
+    }
+
+    if url.starts_with("memory://") {
+        return Ok(Arc::new(MemoryStorage::new()));
+    }
+
+    Ok(Arc::new(PostgresStorage::new(settings)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every backend that runs without a server, migrated and empty.
+    fn backends() -> Vec<(&'static str, Arc<dyn Storage>)> {
+        let sqlite = SqliteStorage::open(":memory:").unwrap();
+        sqlite.migrate().unwrap();
+
+        vec![("memory", Arc::new(MemoryStorage::new())), ("sqlite", Arc::new(sqlite))]
+    }
+
+    fn create_player(database: &dyn Storage, token: &str) -> Player {
+        let mut player = Player::new();
+        player.token = Some(token.to_string());
+
+        database.create_player(&player).unwrap();
+
+        let info = database.load_player(&player).unwrap();
+        player.load(&info).unwrap();
+
+        player
+    }
+
+    fn load_copy(database: &dyn Storage, player: &Player) -> Player {
+        let mut copy = Player::new();
+        copy.token = player.token.clone();
+        copy.load(&database.load_player(player).unwrap()).unwrap();
+
+        copy
+    }
+
+    fn club(name: &str, members: &[LogicLong]) -> Club {
+        Club {
+            id: 0,
+            name: name.to_string(),
+            description: String::new(),
+            badge: 0,
+            club_type: 1,
+            required_trophies: 0,
+            region: "FR".to_string(),
+            members: members.iter().map(|&id| ClubMember { id, role: 2 }).collect(),
+        }
+    }
+
+    /// Adds a club through a backend's test helper, returning its id.
+    type AddClub<'a> = &'a dyn Fn(&Club) -> i32;
+
+    #[test]
+    fn club_members_move_between_clubs() {
+        let memory = MemoryStorage::new();
+        let sqlite = SqliteStorage::open(":memory:").unwrap();
+        sqlite.migrate().unwrap();
+
+        let databases: [(&str, &dyn Storage, AddClub); 2] = [
+            ("memory", &memory, &|club| memory.add_club(club).unwrap()),
+            ("sqlite", &sqlite, &|club| sqlite.add_club(club).unwrap()),
+        ];
+
+        for (backend, database, add_club) in databases {
+            let first = create_player(database, "first").id;
+            let second = create_player(database, "second").id;
+
+            let old = add_club(&club("Old", &[first, second]));
+            let new = add_club(&club("New", &[]));
+
+            database.set_club_member(new, &ClubMember { id: second, role: 2 }).unwrap();
+            assert!(matches!(database.set_club_member(new, &ClubMember { id: LogicLong::new(0, 999), role: 2 }), Err(DatabaseError::NotFound)), "{}", backend);
+
+            let old = database.load_club(old).unwrap().unwrap();
+            let new = database.load_club(new).unwrap().unwrap();
+
+            assert_eq!(old.members.iter().map(|m| m.id).collect::<Vec<_>>(), [first], "{}", backend);
+            assert_eq!(new.members.iter().map(|m| m.id).collect::<Vec<_>>(), [second], "{}", backend);
+
+            let info = database.find_player_by_id(second).unwrap().unwrap();
+            assert_eq!(info.club_id, new.id as u32, "{}", backend);
+
+            database.delete_player(second).unwrap();
+            assert!(database.load_club(new.id).unwrap().unwrap().members.is_empty(), "{}", backend);
+        }
+    }
+
+    #[test]
+    fn players_round_trip() {
+        for (backend, database) in backends() {
+            let mut player = create_player(&*database, "token");
+
+            player.name = "Shelly".to_string();
+            player.gold = 250;
+            player.tokens = 40;
+            player.box_pity.insert("Epic".to_string(), 3);
+            player.mark_dirty();
+
+            database.save_player(&mut player).unwrap();
+
+            let info = database.load_player(&player).unwrap();
+
+            assert_eq!(info.name, "Shelly", "{}", backend);
+            assert_eq!((info.gold, info.tokens), (250, 40), "{}", backend);
+            assert_eq!(info.box_pity.get("Epic"), Some(&3), "{}", backend);
+            assert_eq!(info.revision, player.revision, "{}", backend);
+            assert!(!player.is_dirty(), "{}", backend);
+
+            let by_tag = database.find_player_by_tag(&player.id.to_tag()).unwrap();
+            assert_eq!(by_tag.map(|info| info.id), Some(player.id), "{}", backend);
+        }
+    }
+
+    #[test]
+    fn tokens_are_unique() {
+        for (backend, database) in backends() {
+            create_player(&*database, "token");
+
+            let mut player = Player::new();
+            player.token = Some("token".to_string());
+
+            assert!(matches!(database.create_player(&player), Err(DatabaseError::AlreadyExists)), "{}", backend);
+        }
+    }
+
+    #[test]
+    fn stale_saves_conflict() {
+        for (backend, database) in backends() {
+            let mut first = create_player(&*database, "token");
+            let mut second = load_copy(&*database, &first);
+
+            first.gold = 10;
+            first.mark_dirty();
+            database.save_player(&mut first).unwrap();
+
+            second.gold = 20;
+            second.mark_dirty();
+            assert!(matches!(database.save_player(&mut second), Err(DatabaseError::Conflict)), "{}", backend);
+
+            assert_eq!(database.load_player(&first).unwrap().gold, 10, "{}", backend);
+        }
+    }
+
+    #[test]
+    fn names_are_claimed_once() {
+        for (backend, database) in backends() {
+            let first = create_player(&*database, "first").id;
+            let second = create_player(&*database, "second").id;
+
+            database.claim_name(first, "Ünal").unwrap();
+            database.claim_name(first, "ÜNAL").unwrap();
+
+            assert!(matches!(database.claim_name(second, "ünal"), Err(DatabaseError::AlreadyExists)), "{}", backend);
+            assert!(matches!(database.claim_name(LogicLong::new(0, 999), "Other"), Err(DatabaseError::NotFound)), "{}", backend);
+
+            database.claim_name(first, "Rico").unwrap();
+            database.claim_name(second, "ünal").unwrap();
+
+            database.delete_player(first).unwrap();
+            database.claim_name(second, "rico").unwrap();
+        }
+    }
+
+    #[test]
+    fn sqlite_snapshot_restores() {
+        let source = SqliteStorage::open(":memory:").unwrap();
+        source.migrate().unwrap();
+
+        let mut player = create_player(&source, "token");
+        player.gold = 77;
+        player.mark_dirty();
+        source.save_player(&mut player).unwrap();
+
+        let snapshot = source.snapshot().unwrap();
+
+        let target = SqliteStorage::open(":memory:").unwrap();
+        target.migrate().unwrap();
+        target.restore(&snapshot, false).unwrap();
+
+        assert_eq!(target.load_player(&player).unwrap().gold, 77);
+        assert!(target.restore(&snapshot, false).is_err());
+
+        target.restore(&snapshot, true).unwrap();
+        assert_eq!(target.find_player_by_id(player.id).unwrap().unwrap().gold, 77);
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::logic_long::LogicLong;
+use crate::player::*;
+use crate::csv_logic::game_data;
+
+/// Resources kept in the `resources` table, keyed by name.
+pub const RESOURCE_NAMES: [&str; 7] = ["gems", "gold", "elixir", "chips", "coins_doubler", "coins_booster", "tokens"];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub id: LogicLong,
+    pub club_id: u32,
+    pub club_role: u32,
+    pub player_experience: u32,
+    pub solo_wins: u32,
+    pub duo_wins: u32,
+    pub three_x_three_wins: u32,
+    pub gems: u32,
+    pub gold: u32,
+    pub elixir: u32,
+    pub chips: u32,
+    pub coins_doubler: u32,
+    pub coins_booster: u32,
+    #[serde(default)]
+    pub tokens: u32,
+    pub trophies: u32,
+    pub highest_trophies: u32,
+    pub profile_icon: u32,
+    pub room_id: u32,
+    pub last_connection_time: u32,
+    pub player_status: u32,
+    pub region: String,
+    pub control_mode: u32,
+    pub has_battle_hints: bool,
+    pub unlocked_brawlers: HashMap<i32, BrawlerData>,
+    pub coins_reward: i32,
+    pub event_count: i32,
+    #[serde(default)]
+    pub name_changes: i32,
+    #[serde(default)]
+    pub box_pity: HashMap<String, i32>,
+    #[serde(skip)]
+    pub revision: i64,
+}
+
+impl From<&Player> for PlayerInfo {
+    fn from(player: &Player) -> Self {
+        Self {
+            name: player.name.clone(),
+            id: player.id,
+            club_id: 0,
+            club_role: 0,
+            player_experience: player.player_experience,
+            solo_wins: player.solo_wins,
+            duo_wins: player.duo_wins,
+            three_x_three_wins: player.three_x_three_wins,
+            gems: player.gems,
+            gold: player.gold,
+            elixir: player.elixir,
+            chips: player.chips,
+            coins_doubler: player.coins_doubler,
+            coins_booster: player.coins_booster,
+            tokens: player.tokens,
+            trophies: player.trophies,
+            highest_trophies: player.highest_trophies,
+            profile_icon: player.profile_icon,
+            room_id: player.room_id,
+            last_connection_time: player.last_connection_time,
+            player_status: player.player_status,
+            region: player.region.clone(),
+            control_mode: player.control_mode,
+            has_battle_hints: player.has_battle_hints,
+            unlocked_brawlers: player.unlocked_brawlers.clone(),
+            coins_reward: player.coins_reward,
+            event_count: player.event_count,
+            name_changes: player.name_changes,
+            box_pity: player.box_pity.clone(),
+            revision: player.revision,
+        }
+    }
+}
+
+impl PlayerInfo {
+    /// The data a freshly registered account starts with.
+    pub fn new_account(player: &Player) -> Self {
+        let globals = &game_data().globals;
+
+        Self {
+            gems: globals.starting_diamonds.max(0) as u32,
+            gold: globals.starting_gold.max(0) as u32,
+            club_id: 0,
+            club_role: 0,
+            profile_icon: 0,
+            room_id: 0,
+            last_connection_time: 0,
+            player_status: 0,
+            has_battle_hints: false,
+            ..PlayerInfo::from(player)
+        }
+    }
+
+    pub fn resource(&self, name: &str) -> u32 {
+        match name {
+            "gems" => self.gems,
+            "gold" => self.gold,
+            "elixir" => self.elixir,
+            "chips" => self.chips,
+            "coins_doubler" => self.coins_doubler,
+            "coins_booster" => self.coins_booster,
+            "tokens" => self.tokens,
+            _ => 0,
+        }
+    }
+
+    pub fn set_resource(&mut self, name: &str, amount: u32) {
+        match name {
+            "gems" => self.gems = amount,
+            "gold" => self.gold = amount,
+            "elixir" => self.elixir = amount,
+            "chips" => self.chips = amount,
+            "coins_doubler" => self.coins_doubler = amount,
+            "coins_booster" => self.coins_booster = amount,
+            "tokens" => self.tokens = amount,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClubMember {
+    pub id: LogicLong,
+    pub role: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Club {
+    pub id: i32,
+    pub name: String,
+    pub description: String,
+    pub badge: i32,
+    pub club_type: i32,
+    pub required_trophies: i32,
+    pub region: String,
+    pub members: Vec<ClubMember>,
+}
+
+/// A persisted event slot, `data` is owned by the event system.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EventRecord {
+    pub slot: i32,
+    pub data: String,
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use r2d2::{Pool, PooledConnection};
+use r2d2_postgres::PostgresConnectionManager;
+use postgres::config::SslMode;
+use postgres::{IsolationLevel, Row};
+use postgres_native_tls::MakeTlsConnector;
+use native_tls::TlsConnector;
+
+use log::*;
+use crate::database::*;
+use crate::logic_long::LogicLong;
+use crate::names;
+use crate::player::*;
+use crate::settings::{DatabaseTls, Settings};
+
+pub type DbPool = Pool<PostgresConnectionManager<MakeTlsConnector>>;
+pub type DbConn = PooledConnection<PostgresConnectionManager<MakeTlsConnector>>;
+
+pub struct PostgresStorage {
+    pool: DbPool,
+}
+
+impl PostgresStorage {
+    pub fn new(settings: &Settings) -> Result<Self, DatabaseError> {
+        let connect_timeout = Duration::from_secs(settings.database_connect_timeout);
+
+        let mut config: postgres::Config = settings.database.parse()?;
+        config.connect_timeout(connect_timeout);
+        config.ssl_mode(match settings.database_tls {
+            DatabaseTls::Require => SslMode::Require,
+            DatabaseTls::Prefer => SslMode::Prefer,
+            DatabaseTls::Disable => SslMode::Disable,
+        });
+
+        let tls = MakeTlsConnector::new(TlsConnector::new()?);
+        let manager = PostgresConnectionManager::new(config, tls);
+
+        let pool = Pool::builder()
+            .max_size(settings.database_pool_size)
+            .connection_timeout(connect_timeout)
+            .build(manager)?;
+
+        Ok(Self { pool })
+    }
+
+    pub fn get_conn(&self) -> Result<DbConn, DatabaseError> {
+        Ok(self.pool.get()?)
+    }
+}
+
+fn read_club_member(row: &Row) -> ClubMember {
+    ClubMember {
+        id: LogicLong::new(row.get::<_, i32>("high_id") as u32, row.get::<_, i32>("low_id") as u32),
+        role: row.get("role"),
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn migrate(&self) -> Result<i32, DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        migrations::run(&mut conn)
+    }
+
+    fn create_player(&self, player: &Player) -> Result<LogicLong, DatabaseError> {
+        let token = match player.token.as_deref() {
+            Some(token) => token,
+            None => {
+                error!("deuce: cannot create account if player's token is None");
+                return Err(DatabaseError::NotFound);
+            }
+        };
+
+        if self.token_exists(token)? {
+            error!("deuce: player with token already exists, will not create: {}", token);
+            return Err(DatabaseError::AlreadyExists);
+        }
+
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.transaction()?;
+
+        let (_, id) = players::create(&mut tx, token, &PlayerInfo::new_account(player))?;
+        tx.commit()?;
+
+        Ok(id)
+    }
+
+    fn load_player(&self, player: &Player) -> Result<PlayerInfo, DatabaseError> {
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+        let mut conn = self.get_conn()?;
+
+        players::load_by_token(&mut *conn, token)?.ok_or(DatabaseError::NotFound)
+    }
+
+    fn find_player_by_id(&self, id: LogicLong) -> Result<Option<PlayerInfo>, DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        players::load_by_id(&mut *conn, id)
+    }
+
+    fn save_player(&self, player: &mut Player) -> Result<(), DatabaseError> {
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+        let info = PlayerInfo::from(&*player);
+
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.transaction()?;
+
+        let revision = players::update(&mut tx, token, &info)?.ok_or(DatabaseError::Conflict)?;
+        tx.commit()?;
+
+        player.load_revision(revision);
+
+        Ok(())
+    }
+
+    fn token_exists(&self, token: &str) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        let result = conn.query_one(
+            "SELECT COUNT(*) as count FROM players WHERE token = $1;",
+            &[&token]
+        )?;
+
+        Ok(result.get::<&str, i64>("count") > 0)
+    }
+
+    fn claim_name(&self, id: LogicLong, name: &str) -> Result<(), DatabaseError> {
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.transaction()?;
+
+        let player: i32 = tx.query_opt(
+            "SELECT id FROM players WHERE high_id = $1 AND low_id = $2",
+            &[&(id.high_id as i32), &(id.low_id as i32)]
+        )?.ok_or(DatabaseError::NotFound)?.get("id");
+
+        tx.execute("DELETE FROM player_names WHERE player_id = $1", &[&player])?;
+
+        // a concurrent claim of the same key waits for this one to commit
+        let claimed = tx.execute(
+            "INSERT INTO player_names (name_key, player_id) VALUES ($1, $2) ON CONFLICT (name_key) DO NOTHING",
+            &[&names::key(name), &player]
+        )?;
+
+        if claimed == 0 {
+            return Err(DatabaseError::AlreadyExists);
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn set_token(&self, id: LogicLong, token: &str) -> Result<(), DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        let updated = conn.execute(
+            "UPDATE players SET token = $3 WHERE high_id = $1 AND low_id = $2",
+            &[&(id.high_id as i32), &(id.low_id as i32), &token]
+        )?;
+
+        if updated == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn find_token(&self, id: LogicLong) -> Result<Option<String>, DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        let row = conn.query_opt(
+            "SELECT token FROM players WHERE high_id = $1 AND low_id = $2",
+            &[&(id.high_id as i32), &(id.low_id as i32)]
+        )?;
+
+        Ok(row.map(|row| row.get("token")))
+    }
+
+    fn delete_player(&self, id: LogicLong) -> Result<(), DatabaseError> {
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.transaction()?;
+
+        let row = tx.query_opt(
+            "SELECT id FROM players WHERE high_id = $1 AND low_id = $2",
+            &[&(id.high_id as i32), &(id.low_id as i32)]
+        )?.ok_or(DatabaseError::NotFound)?;
+
+        let player_id: i32 = row.get("id");
+
+        // brawlers, resources, club membership, bans and the claimed name cascade
+        tx.execute("DELETE FROM messages WHERE sender_id = $1", &[&player_id])?;
+        tx.execute("DELETE FROM players WHERE id = $1", &[&player_id])?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn load_club(&self, id: i32) -> Result<Option<Club>, DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        let row = match conn.query_opt(
+            "SELECT name, description, badge, club_type, required_trophies, region FROM clubs WHERE id = $1",
+            &[&id]
+        )? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let members = conn.query(
+            r#"SELECT p.high_id, p.low_id, m.role FROM club_members m
+                JOIN players p ON p.id = m.player_id WHERE m.club_id = $1"#,
+            &[&id]
+        )?;
+
+        Ok(Some(Club {
+            id,
+            name: row.get("name"),
+            description: row.get("description"),
+            badge: row.get("badge"),
+            club_type: row.get("club_type"),
+            required_trophies: row.get("required_trophies"),
+            region: row.get("region"),
+            members: members.iter().map(read_club_member).collect(),
+        }))
+    }
+
+    fn set_club_member(&self, club_id: i32, member: &ClubMember) -> Result<(), DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        let updated = conn.execute(
+            r#"INSERT INTO club_members (club_id, player_id, role)
+                SELECT $1, id, $4 FROM players WHERE high_id = $2 AND low_id = $3
+                ON CONFLICT (player_id) DO UPDATE SET club_id = EXCLUDED.club_id, role = EXCLUDED.role"#,
+            &[&club_id, &(member.id.high_id as i32), &(member.id.low_id as i32), &member.role]
+        )?;
+
+        if updated == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, DatabaseError> {
+        let mut conn = self.get_conn()?;
+        let rows = conn.query("SELECT state, data FROM events WHERE data IS NOT NULL ORDER BY state", &[])?;
+
+        Ok(rows.iter().map(|row| EventRecord { slot: row.get("state"), data: row.get("data") }).collect())
+    }
+
+    fn save_event(&self, event: &EventRecord) -> Result<(), DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        conn.execute(
+            "INSERT INTO events (state, data) VALUES ($1, $2) ON CONFLICT (state) DO UPDATE SET data = EXCLUDED.data",
+            &[&event.slot, &event.data]
+        )?;
+
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Result<Snapshot, DatabaseError> {
+        let mut conn = self.get_conn()?;
+
+        let mut tx = conn.build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()?;
+
+        let schema_version = migrations::current_version(&mut tx)?;
+        let mut tables = HashMap::new();
+
+        for table in SNAPSHOT_TABLES {
+            let row = tx.query_one(
+                &format!("SELECT COALESCE(jsonb_agg(to_jsonb(t)), '[]'::jsonb)::text AS rows FROM {} t", table),
+                &[]
+            )?;
+
+            tables.insert(table.to_string(), serde_json::from_str(row.get("rows"))?);
+        }
+
+        tx.commit()?;
+
+        Ok(Snapshot::new("postgres", schema_version, tables))
+    }
+
+    fn restore(&self, snapshot: &Snapshot, replace: bool) -> Result<(), DatabaseError> {
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.transaction()?;
+
+        snapshot.check("postgres", migrations::current_version(&mut tx)?)?;
+
+        if replace {
+            tx.batch_execute(&format!("TRUNCATE {} RESTART IDENTITY CASCADE", SNAPSHOT_TABLES.join(", ")))?;
+        } else {
+            for table in SNAPSHOT_TABLES {
+                if tx.query_one(&format!("SELECT EXISTS (SELECT 1 FROM {}) AS used", table), &[])?.get("used") {
+                    return Err(DatabaseError::AlreadyExists);
+                }
+            }
+        }
+
+        for table in SNAPSHOT_TABLES {
+            let rows = serde_json::to_string(snapshot.rows(table))?;
+
+            tx.execute(
+                &format!("INSERT INTO {0} SELECT * FROM jsonb_populate_recordset(NULL::{0}, $1::text::jsonb)", table),
+                &[&rows]
+            )?;
+        }
+
+        // the rows came with their ids, move the sequences past them
+        tx.batch_execute(
+            r#"SELECT setval(pg_get_serial_sequence('players', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM players;
+            SELECT setval(pg_get_serial_sequence('clubs', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM clubs;
+            SELECT setval(pg_get_serial_sequence('messages', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM messages;
+            SELECT setval('player_low_id_seq', COALESCE(MAX(low_id), 0) + 1, false) FROM players;"#
+        )?;
+
+        tx.commit()?;
+
+        Ok(())
+    }
+}
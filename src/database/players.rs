@@ -0,0 +1,267 @@
+use postgres::{GenericClient, Row};
+use std::collections::HashMap;
+
+use crate::database::{DatabaseError, PlayerInfo, RESOURCE_NAMES};
+use crate::logic_long::LogicLong;
+use crate::player::BrawlerData;
+
+const SELECT_PLAYER: &str = r#"SELECT p.id, p.high_id, p.low_id, p.name, p.region, p.experience,
+    p.trophies, p.highest_trophies, p.solo_wins, p.duo_wins, p.three_x_three_wins,
+    p.profile_icon, p.room_id, p.control_mode, p.has_battle_hints, p.coins_reward,
+    p.event_count, p.player_status, p.last_connection_time, p.name_changes, p.revision,
+    COALESCE(m.club_id, 0) AS club_id, COALESCE(m.role, 0) AS club_role
+    FROM players p LEFT JOIN club_members m ON m.player_id = p.id"#;
+
+pub fn load_by_token<C: GenericClient>(conn: &mut C, token: &str) -> Result<Option<PlayerInfo>, DatabaseError> {
+    let query = format!("{} WHERE p.token = $1", SELECT_PLAYER);
+    let row = conn.query_opt(query.as_str(), &[&token])?;
+
+    match row {
+        Some(row) => Ok(Some(read(conn, &row)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn load_by_id<C: GenericClient>(conn: &mut C, id: LogicLong) -> Result<Option<PlayerInfo>, DatabaseError> {
+    let query = format!("{} WHERE p.high_id = $1 AND p.low_id = $2 ORDER BY p.id LIMIT 1", SELECT_PLAYER);
+    let row = conn.query_opt(query.as_str(), &[&(id.high_id as i32), &(id.low_id as i32)])?;
+
+    match row {
+        Some(row) => Ok(Some(read(conn, &row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Inserts a new account, allocating its low id from `player_low_id_seq`.
+/// Returns the row id and the allocated id.
+pub fn create<C: GenericClient>(conn: &mut C, token: &str, info: &PlayerInfo) -> Result<(i32, LogicLong), DatabaseError> {
+    let row = conn.query_one("SELECT nextval('player_low_id_seq') AS low_id", &[])?;
+    let id = LogicLong::new(0, row.get::<_, i64>("low_id") as u32);
+
+    let info = PlayerInfo { id, ..info.clone() };
+
+    Ok((insert(conn, token, &info)?, id))
+}
+
+/// Inserts a new player row with all of its brawlers and resources,
+/// returning the row id.
+pub fn insert<C: GenericClient>(conn: &mut C, token: &str, info: &PlayerInfo) -> Result<i32, DatabaseError> {
+    let row = conn.query_one(
+        r#"INSERT INTO players (token, high_id, low_id, name, region, experience,
+            trophies, highest_trophies, solo_wins, duo_wins, three_x_three_wins,
+            profile_icon, room_id, control_mode, has_battle_hints, coins_reward,
+            event_count, player_status, last_connection_time, name_changes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+            RETURNING id"#,
+        &[
+            &token,
+            &(info.id.high_id as i32),
+            &(info.id.low_id as i32),
+            &info.name,
+            &info.region,
+            &(info.player_experience as i32),
+            &(info.trophies as i32),
+            &(info.highest_trophies as i32),
+            &(info.solo_wins as i32),
+            &(info.duo_wins as i32),
+            &(info.three_x_three_wins as i32),
+            &(info.profile_icon as i32),
+            &(info.room_id as i32),
+            &(info.control_mode as i32),
+            &info.has_battle_hints,
+            &info.coins_reward,
+            &info.event_count,
+            &(info.player_status as i32),
+            &(info.last_connection_time as i32),
+            &info.name_changes,
+        ]
+    )?;
+
+    let id: i32 = row.get("id");
+    write_children(conn, id, info)?;
+
+    Ok(id)
+}
+
+/// Updates a player row if its revision still matches `info.revision`,
+/// returning the new revision or `None` when another writer got there first.
+pub fn update<C: GenericClient>(conn: &mut C, token: &str, info: &PlayerInfo) -> Result<Option<i64>, DatabaseError> {
+    let row = conn.query_opt(
+        r#"UPDATE players SET name = $3, region = $4, experience = $5, trophies = $6,
+            highest_trophies = $7, solo_wins = $8, duo_wins = $9, three_x_three_wins = $10,
+            profile_icon = $11, room_id = $12, control_mode = $13, has_battle_hints = $14,
+            coins_reward = $15, event_count = $16, player_status = $17,
+            last_connection_time = $18, name_changes = $19, revision = revision + 1
+            WHERE token = $1 AND revision = $2
+            RETURNING id, revision"#,
+        &[
+            &token,
+            &info.revision,
+            &info.name,
+            &info.region,
+            &(info.player_experience as i32),
+            &(info.trophies as i32),
+            &(info.highest_trophies as i32),
+            &(info.solo_wins as i32),
+            &(info.duo_wins as i32),
+            &(info.three_x_three_wins as i32),
+            &(info.profile_icon as i32),
+            &(info.room_id as i32),
+            &(info.control_mode as i32),
+            &info.has_battle_hints,
+            &info.coins_reward,
+            &info.event_count,
+            &(info.player_status as i32),
+            &(info.last_connection_time as i32),
+            &info.name_changes,
+        ]
+    )?;
+
+    match row {
+        Some(row) => {
+            write_children(conn, row.get("id"), info)?;
+            Ok(Some(row.get("revision")))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Replaces the brawler and resource rows owned by a player.
+pub fn write_children<C: GenericClient>(conn: &mut C, id: i32, info: &PlayerInfo) -> Result<(), DatabaseError> {
+    conn.execute("DELETE FROM brawlers WHERE player_id = $1", &[&id])?;
+    conn.execute("DELETE FROM resources WHERE player_id = $1", &[&id])?;
+    conn.execute("DELETE FROM box_pity WHERE player_id = $1", &[&id])?;
+
+    for (&brawler_id, brawler) in info.unlocked_brawlers.iter() {
+        conn.execute(
+            r#"INSERT INTO brawlers (player_id, brawler_id, selected_skin, trophies,
+                highest_trophies, level, power_points, state, star_power, claimed_rank)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"#,
+            &[
+                &id,
+                &brawler_id,
+                &brawler.selected,
+                &brawler.trophies,
+                &brawler.highest_trophies,
+                &brawler.level,
+                &brawler.power_points,
+                &brawler.state,
+                &brawler.star_power,
+                &brawler.claimed_rank,
+            ]
+        )?;
+
+        for (&card_id, &amount) in brawler.cards.iter() {
+            conn.execute(
+                "INSERT INTO brawler_cards (player_id, brawler_id, card_id, amount) VALUES ($1, $2, $3, $4)",
+                &[&id, &brawler_id, &card_id, &amount]
+            )?;
+        }
+
+        for &skin_id in brawler.skins.iter() {
+            conn.execute(
+                "INSERT INTO brawler_skins (player_id, brawler_id, skin_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                &[&id, &brawler_id, &skin_id]
+            )?;
+        }
+    }
+
+    for name in RESOURCE_NAMES {
+        conn.execute(
+            "INSERT INTO resources (player_id, resource, amount) VALUES ($1, $2, $3)",
+            &[&id, &name, &(info.resource(name) as i32)]
+        )?;
+    }
+
+    for (rarity, &boxes) in info.box_pity.iter() {
+        conn.execute(
+            "INSERT INTO box_pity (player_id, rarity, boxes) VALUES ($1, $2, $3)",
+            &[&id, rarity, &boxes]
+        )?;
+    }
+
+    Ok(())
+}
+
+fn read<C: GenericClient>(conn: &mut C, row: &Row) -> Result<PlayerInfo, DatabaseError> {
+    let id: i32 = row.get("id");
+
+    let mut info = PlayerInfo {
+        name: row.get("name"),
+        id: LogicLong::new(row.get::<_, i32>("high_id") as u32, row.get::<_, i32>("low_id") as u32),
+        club_id: row.get::<_, i32>("club_id") as u32,
+        club_role: row.get::<_, i32>("club_role") as u32,
+        player_experience: row.get::<_, i32>("experience") as u32,
+        solo_wins: row.get::<_, i32>("solo_wins") as u32,
+        duo_wins: row.get::<_, i32>("duo_wins") as u32,
+        three_x_three_wins: row.get::<_, i32>("three_x_three_wins") as u32,
+        gems: 0,
+        gold: 0,
+        elixir: 0,
+        chips: 0,
+        coins_doubler: 0,
+        coins_booster: 0,
+        tokens: 0,
+        trophies: row.get::<_, i32>("trophies") as u32,
+        highest_trophies: row.get::<_, i32>("highest_trophies") as u32,
+        profile_icon: row.get::<_, i32>("profile_icon") as u32,
+        room_id: row.get::<_, i32>("room_id") as u32,
+        last_connection_time: row.get::<_, i32>("last_connection_time") as u32,
+        player_status: row.get::<_, i32>("player_status") as u32,
+        region: row.get("region"),
+        control_mode: row.get::<_, i32>("control_mode") as u32,
+        has_battle_hints: row.get("has_battle_hints"),
+        unlocked_brawlers: HashMap::new(),
+        coins_reward: row.get("coins_reward"),
+        event_count: row.get("event_count"),
+        name_changes: row.get("name_changes"),
+        box_pity: HashMap::new(),
+        revision: row.get("revision"),
+    };
+
+    for row in conn.query("SELECT resource, amount FROM resources WHERE player_id = $1", &[&id])? {
+        let name: String = row.get("resource");
+        info.set_resource(&name, row.get::<_, i32>("amount") as u32);
+    }
+
+    let brawlers = conn.query(
+        r#"SELECT brawler_id, selected_skin, trophies, highest_trophies, level,
+            power_points, state, star_power, claimed_rank FROM brawlers WHERE player_id = $1"#,
+        &[&id]
+    )?;
+
+    for row in brawlers {
+        let brawler = BrawlerData {
+            cards: HashMap::new(),
+            skins: Vec::new(),
+            selected: row.get("selected_skin"),
+            trophies: row.get("trophies"),
+            highest_trophies: row.get("highest_trophies"),
+            level: row.get("level"),
+            power_points: row.get("power_points"),
+            state: row.get("state"),
+            star_power: row.get("star_power"),
+            claimed_rank: row.get("claimed_rank"),
+        };
+
+        info.unlocked_brawlers.insert(row.get("brawler_id"), brawler);
+    }
+
+    for row in conn.query("SELECT brawler_id, card_id, amount FROM brawler_cards WHERE player_id = $1", &[&id])? {
+        if let Some(brawler) = info.unlocked_brawlers.get_mut(&row.get::<_, i32>("brawler_id")) {
+            brawler.cards.insert(row.get("card_id"), row.get("amount"));
+        }
+    }
+
+    for row in conn.query("SELECT brawler_id, skin_id FROM brawler_skins WHERE player_id = $1 ORDER BY skin_id", &[&id])? {
+        if let Some(brawler) = info.unlocked_brawlers.get_mut(&row.get::<_, i32>("brawler_id")) {
+            brawler.skins.push(row.get("skin_id"));
+        }
+    }
+
+    for row in conn.query("SELECT rarity, boxes FROM box_pity WHERE player_id = $1", &[&id])? {
+        info.box_pity.insert(row.get("rarity"), row.get("boxes"));
+    }
+
+    Ok(info)
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::database::DatabaseError;
+
+/// Every deuce table, parents before children so a snapshot can be loaded
+/// in this order without breaking foreign keys.
+pub const SNAPSHOT_TABLES: [&str; 12] = [
+    "players",
+    "player_names",
+    "brawlers",
+    "brawler_cards",
+    "brawler_skins",
+    "resources",
+    "box_pity",
+    "clubs",
+    "club_members",
+    "messages",
+    "events",
+    "bans",
+];
+
+/// A consistent copy of every table, taken inside one transaction.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Backend that took the snapshot. Column types differ between
+    /// backends, so a snapshot only restores into the same kind.
+    pub backend: String,
+    pub schema_version: i32,
+    pub taken_at: i64,
+    /// Rows of each table as json objects keyed by column.
+    pub tables: HashMap<String, Vec<Value>>,
+}
+
+impl Snapshot {
+    pub fn new(backend: &str, schema_version: i32, tables: HashMap<String, Vec<Value>>) -> Self {
+        let taken_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+
+        Self { backend: backend.to_string(), schema_version, taken_at, tables }
+    }
+
+    /// Checks the snapshot was taken by the same backend at the same schema
+    /// version as the database it is restored into.
+    pub fn check(&self, backend: &str, schema_version: i32) -> Result<(), DatabaseError> {
+        if self.backend != backend {
+            return Err(DatabaseError::Incompatible(format!("snapshot of a {} database, this one is {}", self.backend, backend)));
+        }
+
+        if self.schema_version != schema_version {
+            return Err(DatabaseError::Incompatible(format!("snapshot at schema version {}, database at {}", self.schema_version, schema_version)));
+        }
+
+        Ok(())
+    }
+
+    pub fn rows(&self, table: &str) -> &[Value] {
+        self.tables.get(table).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
@@ -0,0 +1,818 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use log::*;
+use crate::database::*;
+use crate::logic_long::LogicLong;
+use crate::names;
+use crate::player::*;
+use crate::token;
+
+/// Schema changes for the SQLite backend, applied in order like the
+/// Postgres migrations.
+const MIGRATIONS: &[(i32, &str, &str)] = &[
+    (1, "initial schema", r#"
+        CREATE TABLE sequences (name TEXT PRIMARY KEY, value INTEGER NOT NULL);
+        INSERT INTO sequences (name, value) VALUES ('player_low_id', 0);
+
+        CREATE TABLE players (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            token TEXT NOT NULL UNIQUE,
+            high_id INTEGER NOT NULL DEFAULT 0,
+            low_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            region TEXT NOT NULL DEFAULT '',
+            experience INTEGER NOT NULL DEFAULT 0,
+            trophies INTEGER NOT NULL DEFAULT 0,
+            highest_trophies INTEGER NOT NULL DEFAULT 0,
+            solo_wins INTEGER NOT NULL DEFAULT 0,
+            duo_wins INTEGER NOT NULL DEFAULT 0,
+            three_x_three_wins INTEGER NOT NULL DEFAULT 0,
+            profile_icon INTEGER NOT NULL DEFAULT 0,
+            room_id INTEGER NOT NULL DEFAULT 0,
+            control_mode INTEGER NOT NULL DEFAULT 0,
+            has_battle_hints INTEGER NOT NULL DEFAULT 0,
+            coins_reward INTEGER NOT NULL DEFAULT 0,
+            event_count INTEGER NOT NULL DEFAULT 4,
+            player_status INTEGER NOT NULL DEFAULT 0,
+            last_connection_time INTEGER NOT NULL DEFAULT 0,
+            revision INTEGER NOT NULL DEFAULT 0,
+            UNIQUE (high_id, low_id)
+        );
+
+        CREATE TABLE brawlers (
+            player_id INTEGER NOT NULL REFERENCES players (id) ON DELETE CASCADE,
+            brawler_id INTEGER NOT NULL,
+            selected_skin INTEGER NOT NULL DEFAULT 0,
+            trophies INTEGER NOT NULL DEFAULT 0,
+            highest_trophies INTEGER NOT NULL DEFAULT 0,
+            level INTEGER NOT NULL DEFAULT 0,
+            power_points INTEGER NOT NULL DEFAULT 0,
+            state INTEGER NOT NULL DEFAULT 0,
+            star_power INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, brawler_id)
+        );
+
+        CREATE TABLE brawler_cards (
+            player_id INTEGER NOT NULL,
+            brawler_id INTEGER NOT NULL,
+            card_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, brawler_id, card_id),
+            FOREIGN KEY (player_id, brawler_id) REFERENCES brawlers (player_id, brawler_id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE brawler_skins (
+            player_id INTEGER NOT NULL,
+            brawler_id INTEGER NOT NULL,
+            skin_id INTEGER NOT NULL,
+            PRIMARY KEY (player_id, brawler_id, skin_id),
+            FOREIGN KEY (player_id, brawler_id) REFERENCES brawlers (player_id, brawler_id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE resources (
+            player_id INTEGER NOT NULL REFERENCES players (id) ON DELETE CASCADE,
+            resource TEXT NOT NULL,
+            amount INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, resource)
+        );
+
+        CREATE TABLE clubs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            badge INTEGER NOT NULL DEFAULT 0,
+            club_type INTEGER NOT NULL DEFAULT 0,
+            required_trophies INTEGER NOT NULL DEFAULT 0,
+            region TEXT NOT NULL DEFAULT ''
+        );
+
+        CREATE TABLE club_members (
+            club_id INTEGER NOT NULL REFERENCES clubs (id) ON DELETE CASCADE,
+            player_id INTEGER NOT NULL UNIQUE REFERENCES players (id) ON DELETE CASCADE,
+            role INTEGER NOT NULL DEFAULT 1,
+            PRIMARY KEY (club_id, player_id)
+        );
+
+        CREATE TABLE messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            channel TEXT NOT NULL,
+            channel_id INTEGER NOT NULL,
+            sender_id INTEGER REFERENCES players (id) ON DELETE SET NULL,
+            kind INTEGER NOT NULL DEFAULT 2,
+            text TEXT NOT NULL DEFAULT '',
+            sent_at INTEGER NOT NULL
+        );
+        CREATE INDEX messages_channel_idx ON messages (channel, channel_id, id);
+
+        CREATE TABLE events (state INTEGER PRIMARY KEY, data TEXT);
+
+        CREATE TABLE bans (
+            player_id INTEGER PRIMARY KEY REFERENCES players (id) ON DELETE CASCADE,
+            reason TEXT NOT NULL DEFAULT '',
+            banned_at INTEGER NOT NULL,
+            expires_at INTEGER
+        );
+    "#),
+    (2, "hashed tokens", r#"
+        UPDATE players SET token = sha256(token);
+    "#),
+    (3, "claimed ranks", r#"
+        ALTER TABLE brawlers ADD COLUMN claimed_rank INTEGER NOT NULL DEFAULT 1;
+    "#),
+    (4, "name changes", r#"
+        ALTER TABLE players ADD COLUMN name_changes INTEGER NOT NULL DEFAULT 0;
+    "#),
+    (5, "box pity", r#"
+        CREATE TABLE box_pity (
+            player_id INTEGER NOT NULL REFERENCES players (id) ON DELETE CASCADE,
+            rarity TEXT NOT NULL,
+            boxes INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (player_id, rarity)
+        );
+    "#),
+    (6, "player names", r#"
+        CREATE TABLE player_names (
+            name_key TEXT PRIMARY KEY,
+            player_id INTEGER NOT NULL UNIQUE REFERENCES players (id) ON DELETE CASCADE
+        );
+
+        INSERT INTO player_names (name_key, player_id)
+            SELECT name_key(name), MIN(id) FROM players WHERE name_key(name) <> 'brawler' GROUP BY name_key(name);
+    "#),
+];
+
+const SELECT_PLAYER: &str = r#"SELECT p.id, p.high_id, p.low_id, p.name, p.region, p.experience,
+    p.trophies, p.highest_trophies, p.solo_wins, p.duo_wins, p.three_x_three_wins,
+    p.profile_icon, p.room_id, p.control_mode, p.has_battle_hints, p.coins_reward,
+    p.event_count, p.player_status, p.last_connection_time, p.name_changes, p.revision,
+    COALESCE(m.club_id, 0) AS club_id, COALESCE(m.role, 0) AS club_role
+    FROM players p LEFT JOIN club_members m ON m.player_id = p.id"#;
+
+/// Subquery resolving `?1`/`?2` (high/low id) to a player row id.
+const PLAYER_ROW: &str = "(SELECT id FROM players WHERE high_id = ?1 AND low_id = ?2)";
+
+/// Tables only the SQLite backend has, snapshotted after the shared ones.
+const SQLITE_TABLES: [&str; 1] = ["sequences"];
+
+fn current_version(conn: &Connection) -> Result<i32, DatabaseError> {
+    Ok(conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))?)
+}
+
+fn dump_table(conn: &Connection, table: &str) -> Result<Vec<Value>, DatabaseError> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let rows = stmt.query_map([], |row| {
+        let mut object = serde_json::Map::new();
+
+        for (i, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(v) => Value::from(v),
+                ValueRef::Real(v) => Value::from(v),
+                ValueRef::Text(v) => Value::from(String::from_utf8_lossy(v).into_owned()),
+                ValueRef::Blob(v) => Value::from(v.to_vec()),
+            };
+
+            object.insert(column.clone(), value);
+        }
+
+        Ok(Value::Object(object))
+    })?;
+
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn load_table(conn: &Connection, table: &str, rows: &[Value]) -> Result<(), DatabaseError> {
+    for row in rows {
+        let Value::Object(object) = row else {
+            return Err(DatabaseError::Incompatible(format!("row of {} is not an object", table)));
+        };
+
+        let columns: Vec<&str> = object.keys().map(String::as_str).collect();
+        let values: Vec<SqlValue> = object.values().map(|value| match value {
+            Value::Null => SqlValue::Null,
+            Value::Bool(v) => SqlValue::Integer(*v as i64),
+            Value::Number(v) => v.as_i64().map(SqlValue::Integer).unwrap_or_else(|| SqlValue::Real(v.as_f64().unwrap_or(0.0))),
+            Value::String(v) => SqlValue::Text(v.clone()),
+            other => SqlValue::Text(other.to_string()),
+        }).collect();
+
+        let placeholders = vec!["?"; columns.len()].join(", ");
+
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO {} ({}) VALUES ({})", table, columns.join(", "), placeholders),
+            rusqlite::params_from_iter(values)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// A single-file database for small deployments.
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> Result<Self, DatabaseError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+
+        // used by the migration that hashes tokens stored before they were hashed
+        conn.create_scalar_function("sha256", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, |ctx| {
+            Ok(token::hash(&ctx.get::<String>(0)?))
+        })?;
+
+        // sqlite's lower() only folds ascii, names are keyed like everywhere else
+        conn.create_scalar_function("name_key", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, |ctx| {
+            Ok(names::key(&ctx.get::<String>(0)?))
+        })?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+}
+
+fn load_where(conn: &Connection, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Option<PlayerInfo>, DatabaseError> {
+    let query = format!("{} WHERE {}", SELECT_PLAYER, filter);
+
+    let row = conn.query_row(&query, params, |row| Ok((row.get::<_, i64>("id")?, read_player(row)?))).optional()?;
+
+    match row {
+        Some((id, mut info)) => {
+            read_children(conn, id, &mut info)?;
+            Ok(Some(info))
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_children(conn: &Connection, id: i64, info: &mut PlayerInfo) -> Result<(), DatabaseError> {
+    let mut stmt = conn.prepare("SELECT resource, amount FROM resources WHERE player_id = ?1")?;
+    let resources = stmt.query_map([id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)))?;
+
+    for resource in resources {
+        let (name, amount) = resource?;
+        info.set_resource(&name, amount);
+    }
+
+    let mut stmt = conn.prepare(
+        r#"SELECT brawler_id, selected_skin, trophies, highest_trophies, level,
+            power_points, state, star_power, claimed_rank FROM brawlers WHERE player_id = ?1"#
+    )?;
+
+    let brawlers = stmt.query_map([id], |row| {
+        Ok((row.get::<_, i32>(0)?, BrawlerData {
+            cards: HashMap::new(),
+            skins: Vec::new(),
+            selected: row.get(1)?,
+            trophies: row.get(2)?,
+            highest_trophies: row.get(3)?,
+            level: row.get(4)?,
+            power_points: row.get(5)?,
+            state: row.get(6)?,
+            star_power: row.get(7)?,
+            claimed_rank: row.get(8)?,
+        }))
+    })?;
+
+    for brawler in brawlers {
+        let (brawler_id, brawler) = brawler?;
+        info.unlocked_brawlers.insert(brawler_id, brawler);
+    }
+
+    let mut stmt = conn.prepare("SELECT brawler_id, card_id, amount FROM brawler_cards WHERE player_id = ?1")?;
+    let cards = stmt.query_map([id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?)))?;
+
+    for card in cards {
+        let (brawler_id, card_id, amount) = card?;
+
+        if let Some(brawler) = info.unlocked_brawlers.get_mut(&brawler_id) {
+            brawler.cards.insert(card_id, amount);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT brawler_id, skin_id FROM brawler_skins WHERE player_id = ?1 ORDER BY skin_id")?;
+    let skins = stmt.query_map([id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?)))?;
+
+    for skin in skins {
+        let (brawler_id, skin_id) = skin?;
+
+        if let Some(brawler) = info.unlocked_brawlers.get_mut(&brawler_id) {
+            brawler.skins.push(skin_id);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT rarity, boxes FROM box_pity WHERE player_id = ?1")?;
+    let pity = stmt.query_map([id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?)))?;
+
+    for counter in pity {
+        let (rarity, boxes) = counter?;
+        info.box_pity.insert(rarity, boxes);
+    }
+
+    Ok(())
+}
+
+fn read_player(row: &Row) -> rusqlite::Result<PlayerInfo> {
+    Ok(PlayerInfo {
+        name: row.get("name")?,
+        id: LogicLong::new(row.get("high_id")?, row.get("low_id")?),
+        club_id: row.get("club_id")?,
+        club_role: row.get("club_role")?,
+        player_experience: row.get("experience")?,
+        solo_wins: row.get("solo_wins")?,
+        duo_wins: row.get("duo_wins")?,
+        three_x_three_wins: row.get("three_x_three_wins")?,
+        gems: 0,
+        gold: 0,
+        elixir: 0,
+        chips: 0,
+        coins_doubler: 0,
+        coins_booster: 0,
+        tokens: 0,
+        trophies: row.get("trophies")?,
+        highest_trophies: row.get("highest_trophies")?,
+        profile_icon: row.get("profile_icon")?,
+        room_id: row.get("room_id")?,
+        last_connection_time: row.get("last_connection_time")?,
+        player_status: row.get("player_status")?,
+        region: row.get("region")?,
+        control_mode: row.get("control_mode")?,
+        has_battle_hints: row.get("has_battle_hints")?,
+        unlocked_brawlers: HashMap::new(),
+        coins_reward: row.get("coins_reward")?,
+        event_count: row.get("event_count")?,
+        name_changes: row.get("name_changes")?,
+        box_pity: HashMap::new(),
+        revision: row.get("revision")?,
+    })
+}
+
+fn insert_player(conn: &Connection, token: &str, info: &PlayerInfo) -> Result<i64, DatabaseError> {
+    conn.execute(
+        r#"INSERT INTO players (token, high_id, low_id, name, region, experience,
+            trophies, highest_trophies, solo_wins, duo_wins, three_x_three_wins,
+            profile_icon, room_id, control_mode, has_battle_hints, coins_reward,
+            event_count, player_status, last_connection_time, name_changes)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)"#,
+        params![
+            token,
+            info.id.high_id,
+            info.id.low_id,
+            info.name,
+            info.region,
+            info.player_experience,
+            info.trophies,
+            info.highest_trophies,
+            info.solo_wins,
+            info.duo_wins,
+            info.three_x_three_wins,
+            info.profile_icon,
+            info.room_id,
+            info.control_mode,
+            info.has_battle_hints,
+            info.coins_reward,
+            info.event_count,
+            info.player_status,
+            info.last_connection_time,
+            info.name_changes,
+        ]
+    )?;
+
+    let id = conn.last_insert_rowid();
+    write_children(conn, id, info)?;
+
+    Ok(id)
+}
+
+/// Replaces the brawler and resource rows owned by a player.
+fn write_children(conn: &Connection, id: i64, info: &PlayerInfo) -> Result<(), DatabaseError> {
+    conn.execute("DELETE FROM brawlers WHERE player_id = ?1", [id])?;
+    conn.execute("DELETE FROM resources WHERE player_id = ?1", [id])?;
+    conn.execute("DELETE FROM box_pity WHERE player_id = ?1", [id])?;
+
+    for (&brawler_id, brawler) in info.unlocked_brawlers.iter() {
+        conn.execute(
+            r#"INSERT INTO brawlers (player_id, brawler_id, selected_skin, trophies,
+                highest_trophies, level, power_points, state, star_power, claimed_rank)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+            params![
+                id,
+                brawler_id,
+                brawler.selected,
+                brawler.trophies,
+                brawler.highest_trophies,
+                brawler.level,
+                brawler.power_points,
+                brawler.state,
+                brawler.star_power,
+                brawler.claimed_rank,
+            ]
+        )?;
+
+        for (&card_id, &amount) in brawler.cards.iter() {
+            conn.execute(
+                "INSERT INTO brawler_cards (player_id, brawler_id, card_id, amount) VALUES (?1, ?2, ?3, ?4)",
+                params![id, brawler_id, card_id, amount]
+            )?;
+        }
+
+        for &skin_id in brawler.skins.iter() {
+            conn.execute(
+                "INSERT OR IGNORE INTO brawler_skins (player_id, brawler_id, skin_id) VALUES (?1, ?2, ?3)",
+                params![id, brawler_id, skin_id]
+            )?;
+        }
+    }
+
+    for name in RESOURCE_NAMES {
+        conn.execute(
+            "INSERT INTO resources (player_id, resource, amount) VALUES (?1, ?2, ?3)",
+            params![id, name, info.resource(name)]
+        )?;
+    }
+
+    for (rarity, &boxes) in info.box_pity.iter() {
+        conn.execute(
+            "INSERT INTO box_pity (player_id, rarity, boxes) VALUES (?1, ?2, ?3)",
+            params![id, rarity, boxes]
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+impl SqliteStorage {
+    /// Adds a club for tests, the server itself never creates one.
+    pub fn add_club(&self, club: &Club) -> Result<i32, DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            r#"INSERT INTO clubs (name, description, badge, club_type, required_trophies, region)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+            params![club.name, club.description, club.badge, club.club_type, club.required_trophies, club.region]
+        )?;
+
+        let id = tx.last_insert_rowid() as i32;
+
+        for member in club.members.iter() {
+            tx.execute(
+                &format!(
+                    r#"INSERT INTO club_members (club_id, player_id, role) SELECT ?3, id, ?4 FROM players
+                        WHERE id = {} ON CONFLICT (player_id) DO UPDATE SET club_id = excluded.club_id, role = excluded.role"#,
+                    PLAYER_ROW
+                ),
+                params![member.id.high_id, member.id.low_id, id, member.role]
+            )?;
+        }
+
+        tx.commit()?;
+
+        Ok(id)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn migrate(&self) -> Result<i32, DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+
+        conn.execute_batch(
+            r#"CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );"#
+        )?;
+
+        let current = current_version(&conn)?;
+        let mut version = current;
+
+        for &(migration, name, sql) in MIGRATIONS.iter().filter(|(v, _, _)| *v > current) {
+            info!("deuce: applying sqlite migration {} ({})", migration, name);
+
+            let tx = conn.transaction()?;
+
+            tx.execute_batch(sql)?;
+            tx.execute("INSERT INTO schema_version (version, name) VALUES (?1, ?2)", params![migration, name])?;
+            tx.commit()?;
+
+            version = migration;
+        }
+
+        Ok(version)
+    }
+
+    fn create_player(&self, player: &Player) -> Result<LogicLong, DatabaseError> {
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+
+        if self.token_exists(token)? {
+            error!("deuce: player with token already exists, will not create: {}", token);
+            return Err(DatabaseError::AlreadyExists);
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let low_id: u32 = tx.query_row(
+            "UPDATE sequences SET value = value + 1 WHERE name = 'player_low_id' RETURNING value",
+            [],
+            |row| row.get(0)
+        )?;
+
+        let id = LogicLong::new(0, low_id);
+
+        insert_player(&tx, token, &PlayerInfo { id, ..PlayerInfo::new_account(player) })?;
+        tx.commit()?;
+
+        Ok(id)
+    }
+
+    fn load_player(&self, player: &Player) -> Result<PlayerInfo, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+
+        load_where(&conn, "p.token = ?1", &[&token])?.ok_or(DatabaseError::NotFound)
+    }
+
+    fn find_player_by_id(&self, id: LogicLong) -> Result<Option<PlayerInfo>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        load_where(&conn, "p.high_id = ?1 AND p.low_id = ?2", &[&id.high_id, &id.low_id])
+    }
+
+    fn save_player(&self, player: &mut Player) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+
+        let token = player.token.as_deref().ok_or(DatabaseError::NotFound)?;
+        let info = PlayerInfo::from(&*player);
+
+        let tx = conn.transaction()?;
+
+        let row: Option<(i64, i64)> = tx.query_row(
+            r#"UPDATE players SET name = ?3, region = ?4, experience = ?5, trophies = ?6,
+                highest_trophies = ?7, solo_wins = ?8, duo_wins = ?9, three_x_three_wins = ?10,
+                profile_icon = ?11, room_id = ?12, control_mode = ?13, has_battle_hints = ?14,
+                coins_reward = ?15, event_count = ?16, player_status = ?17,
+                last_connection_time = ?18, name_changes = ?19, revision = revision + 1
+                WHERE token = ?1 AND revision = ?2
+                RETURNING id, revision"#,
+            params![
+                token,
+                info.revision,
+                info.name,
+                info.region,
+                info.player_experience,
+                info.trophies,
+                info.highest_trophies,
+                info.solo_wins,
+                info.duo_wins,
+                info.three_x_three_wins,
+                info.profile_icon,
+                info.room_id,
+                info.control_mode,
+                info.has_battle_hints,
+                info.coins_reward,
+                info.event_count,
+                info.player_status,
+                info.last_connection_time,
+                info.name_changes,
+            ],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?;
+
+        let (id, revision) = row.ok_or(DatabaseError::Conflict)?;
+
+        write_children(&tx, id, &info)?;
+        tx.commit()?;
+
+        player.load_revision(revision);
+
+        Ok(())
+    }
+
+    fn token_exists(&self, token: &str) -> Result<bool, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM players WHERE token = ?1", [token], |row| row.get(0))?;
+
+        Ok(count > 0)
+    }
+
+    fn claim_name(&self, id: LogicLong, name: &str) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let player: i64 = tx.query_row(&format!("SELECT {}", PLAYER_ROW), params![id.high_id, id.low_id], |row| row.get(0))
+            .optional()?
+            .flatten()
+            .ok_or(DatabaseError::NotFound)?;
+
+        tx.execute("DELETE FROM player_names WHERE player_id = ?1", [player])?;
+
+        let claimed = tx.execute(
+            "INSERT INTO player_names (name_key, player_id) VALUES (?1, ?2) ON CONFLICT (name_key) DO NOTHING",
+            params![names::key(name), player]
+        )?;
+
+        if claimed == 0 {
+            return Err(DatabaseError::AlreadyExists);
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn set_token(&self, id: LogicLong, token: &str) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        let updated = conn.execute(
+            "UPDATE players SET token = ?3 WHERE high_id = ?1 AND low_id = ?2",
+            params![id.high_id, id.low_id, token]
+        )?;
+
+        if updated == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn find_token(&self, id: LogicLong) -> Result<Option<String>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        Ok(conn.query_row(
+            "SELECT token FROM players WHERE high_id = ?1 AND low_id = ?2",
+            params![id.high_id, id.low_id],
+            |row| row.get(0)
+        ).optional()?)
+    }
+
+    fn delete_player(&self, id: LogicLong) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let player_id: i64 = tx.query_row(
+            "SELECT id FROM players WHERE high_id = ?1 AND low_id = ?2",
+            params![id.high_id, id.low_id],
+            |row| row.get(0)
+        ).optional()?.ok_or(DatabaseError::NotFound)?;
+
+        // brawlers, resources, club membership, bans and the claimed name cascade
+        tx.execute("DELETE FROM messages WHERE sender_id = ?1", [player_id])?;
+        tx.execute("DELETE FROM players WHERE id = ?1", [player_id])?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn load_club(&self, id: i32) -> Result<Option<Club>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        let club = conn.query_row(
+            "SELECT name, description, badge, club_type, required_trophies, region FROM clubs WHERE id = ?1",
+            [id],
+            |row| Ok(Club {
+                id,
+                name: row.get(0)?,
+                description: row.get(1)?,
+                badge: row.get(2)?,
+                club_type: row.get(3)?,
+                required_trophies: row.get(4)?,
+                region: row.get(5)?,
+                members: Vec::new(),
+            })
+        ).optional()?;
+
+        let mut club = match club {
+            Some(club) => club,
+            None => return Ok(None),
+        };
+
+        let mut stmt = conn.prepare(
+            r#"SELECT p.high_id, p.low_id, m.role FROM club_members m
+                JOIN players p ON p.id = m.player_id WHERE m.club_id = ?1"#
+        )?;
+
+        let members = stmt.query_map([id], |row| Ok(ClubMember {
+            id: LogicLong::new(row.get(0)?, row.get(1)?),
+            role: row.get(2)?,
+        }))?;
+
+        for member in members {
+            club.members.push(member?);
+        }
+
+        Ok(Some(club))
+    }
+
+    fn set_club_member(&self, club_id: i32, member: &ClubMember) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        let updated = conn.execute(
+            &format!(
+                r#"INSERT INTO club_members (club_id, player_id, role) SELECT ?3, id, ?4 FROM players
+                    WHERE id = {} ON CONFLICT (player_id) DO UPDATE SET club_id = excluded.club_id, role = excluded.role"#,
+                PLAYER_ROW
+            ),
+            params![member.id.high_id, member.id.low_id, club_id, member.role]
+        )?;
+
+        if updated == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare("SELECT state, data FROM events WHERE data IS NOT NULL ORDER BY state")?;
+        let events = stmt.query_map([], |row| Ok(EventRecord { slot: row.get(0)?, data: row.get(1)? }))?;
+
+        Ok(events.collect::<rusqlite::Result<Vec<EventRecord>>>()?)
+    }
+
+    fn save_event(&self, event: &EventRecord) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO events (state, data) VALUES (?1, ?2) ON CONFLICT (state) DO UPDATE SET data = excluded.data",
+            params![event.slot, event.data]
+        )?;
+
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Result<Snapshot, DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let schema_version = current_version(&tx)?;
+        let mut tables = HashMap::new();
+
+        for table in SNAPSHOT_TABLES.iter().chain(SQLITE_TABLES.iter()) {
+            tables.insert(table.to_string(), dump_table(&tx, table)?);
+        }
+
+        tx.commit()?;
+
+        Ok(Snapshot::new("sqlite", schema_version, tables))
+    }
+
+    fn restore(&self, snapshot: &Snapshot, replace: bool) -> Result<(), DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        snapshot.check("sqlite", current_version(&tx)?)?;
+
+        for table in SNAPSHOT_TABLES.iter().rev() {
+            if replace {
+                tx.execute(&format!("DELETE FROM {}", table), [])?;
+            } else if tx.query_row(&format!("SELECT EXISTS (SELECT 1 FROM {})", table), [], |row| row.get(0))? {
+                return Err(DatabaseError::AlreadyExists);
+            }
+        }
+
+        // sequences always have their rows, they are overwritten in place
+        for table in SNAPSHOT_TABLES.iter().chain(SQLITE_TABLES.iter()) {
+            load_table(&tx, table, snapshot.rows(table))?;
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_ordered() {
+        for (i, &(version, _, _)) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(version, i as i32 + 1);
+        }
+    }
+
+    #[test]
+    fn migrate_twice() {
+        let database = SqliteStorage::open(":memory:").unwrap();
+        let latest = MIGRATIONS.last().unwrap().0;
+
+        assert_eq!(database.migrate().unwrap(), latest);
+        assert_eq!(database.migrate().unwrap(), latest);
+    }
+}
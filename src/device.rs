@@ -1,21 +1,27 @@
-use std::collections::HashMap;
 use std::io::Write;
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
-use crate::network::ClientInfo;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use log::*;
+use crate::logic_long::LogicLong;
+use crate::network::{Clients, Kick};
 use crate::rc4::Rc4;
 use crate::settings::Settings;
 
+/// How long a new login waits for the session it replaces to save.
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct Device {
+    client_id: String,
     stream: TcpStream,
-    clients: Arc<Mutex<HashMap<String, ClientInfo>>>,
+    clients: Clients,
 
     decryptor: Rc4,
     encryptor: Rc4,
 }
 
 impl Device {
-    pub fn new(settings: Arc<Settings>, stream: TcpStream, clients: Arc<Mutex<HashMap<String, ClientInfo>>>) -> Self {
+    pub fn new(settings: Arc<Settings>, client_id: String, stream: TcpStream, clients: Clients) -> Self {
         let binding = settings.key.clone();
         let key = binding.as_bytes();
         let nonce = b"nonce";
@@ -32,6 +38,7 @@ impl Device {
         encryptor.process(&mut full_key);
 
         Self {
+            client_id,
             stream,
             clients,
 
@@ -40,6 +47,51 @@ impl Device {
         }
     }
 
+    /// Claims the account for this connection. If another connection is
+    /// logged in as the same account it is told to disconnect, and this
+    /// blocks until it has saved its player. Returns whether a session was
+    /// taken over.
+    pub fn take_over(&self, account: LogicLong) -> bool {
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let mut clients = self.clients.lock().unwrap();
+
+            if let Some(me) = clients.get_mut(&self.client_id) {
+                me.account = Some(account);
+            }
+
+            let old = clients.iter_mut()
+                .find(|(id, info)| **id != self.client_id && info.account == Some(account) && info.kick.is_none());
+
+            match old {
+                Some((_, info)) => {
+                    info.kick = Some(Kick::Takeover(tx));
+
+                    // wakes the old session up, it still has its write side to say goodbye
+                    if let Err(e) = info.stream.shutdown(Shutdown::Read) {
+                        error!("deuce: failed to shut down old session of {}: {}", account, e);
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        info!("deuce: player {} logged in elsewhere, waiting for old session", account);
+
+        if rx.recv_timeout(TAKEOVER_TIMEOUT).is_err() {
+            error!("deuce: old session of {} did not save in time", account);
+        }
+
+        true
+    }
+
+    /// Returns why the server closed this session, if it did.
+    pub fn take_kick(&self) -> Option<Kick> {
+        let mut clients = self.clients.lock().unwrap();
+        clients.get_mut(&self.client_id).and_then(|info| info.kick.take())
+    }
+
     pub fn decrypt(&mut self, data: &mut [u8]) {
         self.decryptor.process(data);
     }
@@ -66,6 +118,9 @@ impl Device {
         packet.extend_from_slice(&version.to_be_bytes());
         packet.extend_from_slice(binding);
         
-        self.stream.write_all(&packet).unwrap();
+        // the peer may already be gone, the read side notices and ends the session
+        if let Err(e) = self.stream.write_all(&packet) {
+            error!("deuce: failed to send packet {}: {}", packet_id, e);
+        }
     }
 }
@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::csv_logic::{game_data, GameData, LocationRow};
+use crate::database::{EventRecord, Storage};
+use crate::settings::{EventPlan, EventSlot};
+
+/// Seconds between checks for ended events.
+const TICK: Duration = Duration::from_secs(10);
+
+static EVENTS: RwLock<Vec<Event>> = RwLock::new(Vec::new());
+
+/// What an event slot is showing, persisted as the slot's `data` in the
+/// events table.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Event {
+    /// Slot number, counting from 1.
+    #[serde(skip)]
+    pub slot: i32,
+    /// Name of the location in locations.csv.
+    pub location: String,
+    /// Location that takes over once this one ends.
+    pub next: String,
+    pub started_at: u64,
+    pub ends_at: u64,
+    /// Start time of the special the event belongs to, `None` for the
+    /// regular rotation. No two specials of a slot start together, so this
+    /// still finds the special after others are added or removed.
+    #[serde(default)]
+    pub special: Option<u64>,
+    #[serde(skip)]
+    pub banner: String,
+    /// Multiplier for battle rewards earned in this event.
+    #[serde(skip)]
+    pub reward_multiplier: u32,
+}
+
+impl Event {
+    /// Seconds until the event ends.
+    pub fn time_left(&self, now: u64) -> u64 {
+        self.ends_at.saturating_sub(now)
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+/// The events running right now, one per slot that has locations.
+pub fn current() -> Vec<Event> {
+    EVENTS.read().unwrap().clone()
+}
+
+/// Multiplier for battle rewards at `location`: the highest of the events
+/// running there, 1 if there are none.
+pub fn reward_multiplier(events: &[Event], location: &str, now: u64) -> u32 {
+    events.iter()
+        .filter(|event| event.location == location && event.started_at <= now && now < event.ends_at)
+        .map(|event| event.reward_multiplier)
+        .max()
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Locations a slot rotates through: its explicit list, or every location
+/// of its modes in locations.csv order. Locations whose maps are not in
+/// maps.csv are left out.
+fn rotation<'a>(data: &'a GameData, slot: &EventSlot) -> Vec<&'a LocationRow> {
+    let locations: Vec<&LocationRow> = if slot.locations.is_empty() {
+        data.locations.iter().filter(|location| slot.modes.contains(&location.game_mode)).collect()
+    } else {
+        slot.locations.iter().filter_map(|name| data.location_by_name(name)).collect()
+    };
+
+    locations.into_iter().filter(|location| data.map_by_name(&location.allowed_maps).is_some()).collect()
+}
+
+/// Number of slots the plan can show, regular ones first.
+fn slot_count(plan: &EventPlan) -> usize {
+    plan.specials.iter().map(|special| special.slot).max().unwrap_or(0).max(plan.slots.len())
+}
+
+/// The rotation a slot follows at `time`: a special running on it, or its
+/// regular one.
+fn active(plan: &EventPlan, number: usize, time: u64) -> Option<(Option<u64>, &EventSlot)> {
+    plan.specials.iter()
+        .find(|special| special.slot == number && (special.start..special.end).contains(&time))
+        .map(|special| (Some(special.start), &special.rotation))
+        .or_else(|| plan.slots.get(number - 1).map(|slot| (None, slot)))
+}
+
+/// When the rotation of a slot changes after `time`: the running special
+/// ends or the next one starts.
+fn next_change(plan: &EventPlan, number: usize, special: Option<u64>, time: u64) -> Option<u64> {
+    let specials = plan.specials.iter().filter(|special| special.slot == number);
+
+    match special {
+        Some(start) => specials.filter(|special| special.start == start).map(|special| special.end).next(),
+        None => specials.filter(|special| special.start > time).map(|special| special.start).min(),
+    }
+}
+
+/// Moves a slot on to the location that should be up at `now`, skipping the
+/// ones that ended while the server was down. Returns `None` if the event
+/// is still running or the slot has nothing to show.
+fn advance(data: &GameData, plan: &EventPlan, number: usize, event: Option<&Event>, now: u64) -> Option<Event> {
+    let (special, slot) = active(plan, number, now)?;
+    let locations = rotation(data, slot);
+    let duration = slot.duration.max(1);
+
+    if locations.is_empty() {
+        return None;
+    }
+
+    let position = event
+        .filter(|event| event.special == special)
+        .and_then(|event| locations.iter().position(|location| location.name == event.location));
+
+    let (index, started_at) = match (event, position) {
+        (Some(event), Some(_)) if now < event.ends_at => return None,
+        (Some(event), Some(position)) => {
+            let skipped = (now - event.ends_at) / duration;
+            (position + 1 + skipped as usize, event.ends_at + skipped * duration)
+        }
+        // new slot, or its rotation changed
+        _ => (0, now),
+    };
+
+    let mut ends_at = started_at + duration;
+
+    if let Some(change) = next_change(plan, number, special, now) {
+        ends_at = ends_at.min(change);
+    }
+
+    let next = match active(plan, number, ends_at) {
+        Some((upcoming, _)) if upcoming == special => Some(locations[(index + 1) % locations.len()]),
+        Some((_, upcoming)) => rotation(data, upcoming).first().copied(),
+        None => None,
+    };
+
+    Some(Event {
+        slot: number as i32,
+        location: locations[index % locations.len()].name.clone(),
+        next: next.map_or_else(String::new, |location| location.name.clone()),
+        started_at,
+        ends_at,
+        special,
+        banner: slot.banner.clone().unwrap_or_else(|| plan.banner.clone()),
+        reward_multiplier: slot.reward_multiplier,
+    })
+}
+
+/// Brings every slot of the plan up to date at `now`. Returns the events
+/// to show and the slots that moved on to a new event.
+fn update(data: &GameData, plan: &EventPlan, events: Vec<Event>, now: u64) -> (Vec<Event>, Vec<usize>) {
+    let mut current: HashMap<usize, Event> = events.into_iter().map(|event| (event.slot as usize, event)).collect();
+    let mut changed = Vec::new();
+
+    for number in 1..=slot_count(plan) {
+        let Some((special, slot)) = active(plan, number, now) else {
+            current.remove(&number);
+            continue;
+        };
+
+        let Some(event) = advance(data, plan, number, current.get(&number), now) else {
+            // still running, pick up banner and rewards in case the plan changed
+            if let Some(event) = current.get_mut(&number).filter(|event| event.special == special) {
+                event.banner = slot.banner.clone().unwrap_or_else(|| plan.banner.clone());
+                event.reward_multiplier = slot.reward_multiplier;
+            }
+
+            continue;
+        };
+
+        info!(
+            "deuce: event slot {} is now {} until {} (rewards x{})",
+            number, event.location, event.ends_at, event.reward_multiplier
+        );
+
+        current.insert(number, event);
+        changed.push(number);
+    }
+
+    let events = (1..=slot_count(plan)).filter_map(|number| current.remove(&number)).collect();
+
+    (events, changed)
+}
+
+fn tick(database: &dyn Storage, plan: &EventPlan, now: u64) {
+    // only this thread writes the events, so they cannot change until the swap
+    let (events, changed) = update(&game_data(), plan, current(), now);
+
+    for event in events.iter().filter(|event| changed.contains(&(event.slot as usize))) {
+        let record = EventRecord { slot: event.slot, data: serde_json::to_string(event).unwrap() };
+
+        if let Err(e) = database.save_event(&record) {
+            error!("deuce: failed to save event slot {}: {}", event.slot, e);
+        }
+    }
+
+    *EVENTS.write().unwrap() = events;
+}
+
+/// Checks the plan against locations.csv, listing every problem found.
+pub fn check_plan(plan: &EventPlan, data: &GameData) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let slots = plan.slots.iter().enumerate().map(|(i, slot)| (format!("event slot {}", i + 1), slot));
+    let specials = plan.specials.iter().enumerate()
+        .map(|(i, special)| (format!("special {} (slot {})", i + 1, special.slot), &special.rotation));
+
+    for (name, slot) in slots.chain(specials) {
+        for mode in slot.modes.iter().filter(|mode| !data.locations.iter().any(|location| location.game_mode == **mode)) {
+            problems.push(format!("{}: no location has game mode {}", name, mode));
+        }
+
+        for location in &slot.locations {
+            match data.location_by_name(location) {
+                None => problems.push(format!("{}: {} is not in locations.csv", name, location)),
+                Some(row) if data.map_by_name(&row.allowed_maps).is_none() => {
+                    problems.push(format!("{}: {} has no map in maps.csv", name, location))
+                }
+                Some(_) => {}
+            }
+        }
+
+        if slot.duration == 0 {
+            problems.push(format!("{}: duration is 0", name));
+        }
+
+        if rotation(data, slot).is_empty() {
+            problems.push(format!("{}: no locations to rotate through", name));
+        }
+    }
+
+    for (i, special) in plan.specials.iter().enumerate() {
+        if special.slot == 0 {
+            problems.push(format!("special {}: slots count from 1", i + 1));
+        }
+
+        if special.end <= special.start {
+            problems.push(format!("special {}: ends before it starts", i + 1));
+        }
+
+        if let Some(j) = plan.specials[..i].iter().position(|other| other.slot == special.slot && other.start == special.start) {
+            problems.push(format!("special {}: starts together with special {} on slot {}", i + 1, j + 1, special.slot));
+        }
+    }
+
+    problems
+}
+
+/// Restores the persisted events, brings them up to date and keeps
+/// rotating them in the background.
+pub fn start(database: Arc<dyn Storage>, plan: EventPlan) {
+    let persisted = database.load_events().unwrap_or_else(|e| {
+        error!("deuce: failed to load events, starting new rotations: {}", e);
+        Vec::new()
+    });
+
+    *EVENTS.write().unwrap() = persisted.iter()
+        .filter_map(|record| {
+            let event: Event = serde_json::from_str(&record.data).ok()?;
+            Some(Event { slot: record.slot, ..event })
+        })
+        .collect();
+
+    tick(database.as_ref(), &plan, now());
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        tick(database.as_ref(), &plan, now());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::SpecialEvent;
+
+    fn slot(locations: &[&str], duration: u64, reward_multiplier: u32) -> EventSlot {
+        EventSlot {
+            modes: Vec::new(),
+            locations: locations.iter().map(|name| name.to_string()).collect(),
+            duration,
+            banner: None,
+            reward_multiplier,
+        }
+    }
+
+    fn plan(specials: Vec<SpecialEvent>) -> EventPlan {
+        EventPlan { slots: vec![slot(&["Wanted1", "Wanted2"], 100, 1)], specials, ..EventPlan::default() }
+    }
+
+    fn event(location: &str, started_at: u64, ends_at: u64) -> Event {
+        Event {
+            slot: 1,
+            location: location.to_string(),
+            next: String::new(),
+            started_at,
+            ends_at,
+            special: None,
+            banner: String::new(),
+            reward_multiplier: 1,
+        }
+    }
+
+    #[test]
+    fn new_slots_start_at_the_first_location() {
+        let (events, changed) = update(&game_data(), &plan(Vec::new()), Vec::new(), 1000);
+
+        assert_eq!(changed, [1]);
+        assert_eq!((events[0].location.as_str(), events[0].next.as_str()), ("Wanted1", "Wanted2"));
+        assert_eq!((events[0].started_at, events[0].ends_at), (1000, 1100));
+    }
+
+    #[test]
+    fn running_events_stay() {
+        let (events, changed) = update(&game_data(), &plan(Vec::new()), vec![event("Wanted1", 1000, 1100)], 1099);
+
+        assert!(changed.is_empty());
+        assert_eq!((events[0].location.as_str(), events[0].ends_at), ("Wanted1", 1100));
+    }
+
+    #[test]
+    fn advance_catches_up_on_missed_events() {
+        let data = game_data();
+        let plan = plan(Vec::new());
+
+        let next = advance(&data, &plan, 1, Some(&event("Wanted1", 1000, 1100)), 1100).unwrap();
+        assert_eq!((next.location.as_str(), next.started_at, next.ends_at), ("Wanted2", 1100, 1200));
+
+        // Wanted2 and Wanted1 ran while the server was down
+        let next = advance(&data, &plan, 1, Some(&event("Wanted1", 1000, 1100)), 1350).unwrap();
+        assert_eq!((next.location.as_str(), next.started_at, next.ends_at), ("Wanted2", 1300, 1400));
+        assert_eq!(next.next, "Wanted1");
+    }
+
+    #[test]
+    fn reward_multipliers() {
+        let mut doubled = event("Gemgrab1", 1000, 1100);
+        doubled.reward_multiplier = 2;
+        let events = [event("Wanted1", 1000, 1100), doubled];
+
+        assert_eq!(reward_multiplier(&events, "Gemgrab1", 1050), 2);
+        assert_eq!(reward_multiplier(&events, "Wanted1", 1050), 1);
+        assert_eq!(reward_multiplier(&events, "Gemgrab1", 1100), 1);
+        assert_eq!(reward_multiplier(&events, "Gemgrab2", 1050), 1);
+    }
+
+    #[test]
+    fn unknown_locations_restart_the_rotation() {
+        let next = advance(&game_data(), &plan(Vec::new()), 1, Some(&event("Removed", 1000, 1100)), 1050).unwrap();
+
+        assert_eq!((next.location.as_str(), next.started_at), ("Wanted1", 1050));
+    }
+
+    #[test]
+    fn specials_take_over_and_hand_back() {
+        let special = SpecialEvent { slot: 1, start: 1050, end: 1300, rotation: slot(&["Gemgrab1"], 1000, 2) };
+        let extra = SpecialEvent { slot: 2, start: 1000, end: 1200, rotation: slot(&["Gemgrab2"], 50, 3) };
+        let plan = plan(vec![special, extra]);
+        let data = game_data();
+
+        // the regular event is cut short by the special
+        let (events, _) = update(&data, &plan, Vec::new(), 1000);
+        assert_eq!((events[0].location.as_str(), events[0].ends_at, events[0].next.as_str()), ("Wanted1", 1050, "Gemgrab1"));
+        assert_eq!((events[1].location.as_str(), events[1].reward_multiplier), ("Gemgrab2", 3));
+
+        let (events, _) = update(&data, &plan, events, 1060);
+        assert_eq!((events[0].location.as_str(), events[0].special, events[0].ends_at), ("Gemgrab1", Some(1050), 1300));
+        assert_eq!(events[0].reward_multiplier, 2);
+        assert_eq!(events[0].next, "Wanted1");
+
+        // the extra slot is only shown while its special runs
+        let (events, _) = update(&data, &plan, events, 1300);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].location.as_str(), events[0].special), ("Wanted1", None));
+    }
+
+    #[test]
+    fn specials_survive_plan_edits() {
+        let special = || SpecialEvent { slot: 1, start: 1000, end: 5000, rotation: slot(&["Gemgrab1", "Gemgrab2"], 100, 2) };
+        let data = game_data();
+
+        let (events, _) = update(&data, &plan(vec![special()]), Vec::new(), 1000);
+        let (events, _) = update(&data, &plan(vec![special()]), events, 1100);
+        assert_eq!((events[0].location.as_str(), events[0].started_at), ("Gemgrab2", 1100));
+
+        // a special added in front keeps the running one where it was
+        let earlier = SpecialEvent { slot: 1, start: 100, end: 200, rotation: slot(&["Gemgrab1"], 100, 1) };
+        let (events, changed) = update(&data, &plan(vec![earlier, special()]), events, 1150);
+        assert!(changed.is_empty());
+        assert_eq!((events[0].location.as_str(), events[0].reward_multiplier), ("Gemgrab2", 2));
+
+        // a special that moved is a new one and starts over
+        let mut moved = special();
+        moved.start = 1001;
+        let (events, changed) = update(&data, &plan(vec![moved]), events, 1150);
+        assert_eq!(changed, [1]);
+        assert_eq!((events[0].location.as_str(), events[0].special, events[0].started_at), ("Gemgrab1", Some(1001), 1150));
+    }
+}
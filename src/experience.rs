@@ -0,0 +1,189 @@
+use log::*;
+
+use crate::csv_logic::{game_data, GameData, Milestone, EXPERIENCE_LEVEL};
+use crate::device::Device;
+use crate::milestones::Milestones;
+use crate::packets::commands::{DeliveryCommand, ExperienceCommand};
+use crate::packets::packet::ServerPacket;
+use crate::packets::server::AvailableServerCommandMessage;
+use crate::player::Player;
+use crate::settings::Settings;
+
+/// Columns holding the experience level a row unlocks at, by table.
+const LEVEL_GATES: &[(&str, &str)] = &[
+    ("player_thumbnails", "RequiredExpLevel"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BattleResult {
+    Victory,
+    Draw,
+    Defeat,
+}
+
+/// What adding experience did to a player.
+pub struct ExperienceGain {
+    pub experience: i32,
+    pub level: i32,
+    /// Level-up steps whose rewards were granted.
+    pub rewards: Vec<Milestone>,
+    /// Table and name of every row the new levels unlocked.
+    pub unlocks: Vec<(&'static str, String)>,
+}
+
+/// Experience level for `experience`, counting from 1.
+pub fn level(experience: u32, settings: &Settings) -> i32 {
+    let data = game_data();
+
+    data.milestone_track(EXPERIENCE_LEVEL).map_or(1, |track| {
+        1 + track.level(experience as i32, track.step_count(settings.max_rank)) as i32
+    })
+}
+
+/// Level gates whose table or column is not in the data, which would
+/// otherwise never unlock anything.
+pub fn check_gates(data: &GameData) -> Vec<String> {
+    LEVEL_GATES.iter().filter_map(|&(table, column)| match data.table(table) {
+        None => Some(format!("level gate {}.{}: {}.csv is missing", table, column, table)),
+        Some(rows) if rows.columns().position(column).is_none() => {
+            Some(format!("level gate {}.{}: {}.csv has no {} column", table, column, table, column))
+        }
+        Some(_) => None,
+    }).collect()
+}
+
+/// Rows unlocked by going from level `from` to level `to`.
+pub fn unlocks(from: i32, to: i32) -> Vec<(&'static str, String)> {
+    let data = game_data();
+
+    LEVEL_GATES.iter()
+        .filter_map(|&(table, column)| Some((table, column, data.table(table)?)))
+        .flat_map(|(table, column, rows)| {
+            rows.rows().iter()
+                .filter(move |row| (from + 1..=to).contains(&row.int(column)))
+                .map(move |row| (table, row.name().to_string()))
+        })
+        .collect()
+}
+
+/// Experience a battle gives, from globals.csv.
+pub fn battle_experience(result: BattleResult, star_player: bool) -> i32 {
+    let globals = &game_data().globals;
+
+    let base = match result {
+        BattleResult::Victory => globals.exp_for_result[0],
+        BattleResult::Draw => globals.exp_for_result[1],
+        BattleResult::Defeat => globals.exp_for_result[2],
+    };
+
+    if star_player {
+        base + globals.exp_for_star_player
+    } else {
+        base
+    }
+}
+
+/// Adds experience to a player, granting the rewards of every level
+/// reached on the way.
+pub fn add_experience(player: &mut Player, settings: &Settings, amount: i32) -> ExperienceGain {
+    let from = player.player_experience;
+    let to = from.saturating_add_signed(amount.max(0));
+
+    player.player_experience = to;
+    player.mark_dirty();
+
+    let rewards = Milestones::new(settings).grant(player, EXPERIENCE_LEVEL, from as i32, to as i32);
+    let (old_level, new_level) = (level(from, settings), level(to, settings));
+
+    if new_level > old_level {
+        info!("deuce: player {} reached experience level {}", player.id, new_level);
+    }
+
+    ExperienceGain {
+        experience: (to - from) as i32,
+        level: new_level,
+        rewards,
+        unlocks: unlocks(old_level, new_level),
+    }
+}
+
+pub fn add_battle_result(player: &mut Player, settings: &Settings, result: BattleResult, star_player: bool, multiplier: u32) -> ExperienceGain {
+    add_experience(player, settings, battle_experience(result, star_player).saturating_mul(i32::try_from(multiplier).unwrap_or(i32::MAX)))
+}
+
+impl ExperienceGain {
+    /// Tells the client about the experience and the level-up rewards. The
+    /// client works out levels and unlocks from the experience itself.
+    pub fn notify(&self, device: &mut Device) {
+        let mut experience = AvailableServerCommandMessage::new(Box::new(ExperienceCommand::new(self.experience)));
+        device.send(experience.id, experience.encode(), 0);
+
+        if !self.rewards.is_empty() {
+            let rewards: Vec<_> = self.rewards.iter().map(|milestone| &milestone.reward).collect();
+            let mut delivery = AvailableServerCommandMessage::new(Box::new(DeliveryCommand::new(&rewards)));
+            device.send(delivery.id, delivery.encode(), 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::tests::parse;
+
+    #[test]
+    fn battle_rewards_follow_globals() {
+        let globals = &game_data().globals;
+
+        assert_eq!(battle_experience(BattleResult::Victory, false), globals.exp_for_result[0]);
+        assert_eq!(battle_experience(BattleResult::Draw, false), globals.exp_for_result[1]);
+        assert_eq!(battle_experience(BattleResult::Defeat, true), globals.exp_for_result[2] + globals.exp_for_star_player);
+    }
+
+    #[test]
+    fn battle_results_are_multiplied() {
+        let settings = parse("").unwrap();
+        let mut player = Player::new();
+
+        let gain = add_battle_result(&mut player, &settings, BattleResult::Draw, false, 2);
+
+        assert_eq!(gain.experience, 2 * battle_experience(BattleResult::Draw, false));
+    }
+
+    #[test]
+    fn level_thresholds() {
+        let settings = parse("").unwrap();
+
+        // 40 to reach level 2, then 10 more every level
+        assert_eq!(level(0, &settings), 1);
+        assert_eq!(level(39, &settings), 1);
+        assert_eq!(level(40, &settings), 2);
+        assert_eq!(level(89, &settings), 2);
+        assert_eq!(level(90, &settings), 3);
+        assert_eq!(level(150, &settings), 4);
+    }
+
+    #[test]
+    fn level_unlocks() {
+        assert_eq!(unlocks(9, 10), [("player_thumbnails", "base2".to_string())]);
+        assert_eq!(unlocks(1, 20).len(), 2);
+        assert!(unlocks(10, 19).is_empty());
+        assert!(check_gates(&game_data()).is_empty());
+    }
+
+    #[test]
+    fn adding_experience_grants_level_rewards() {
+        let settings = parse("").unwrap();
+        let mut player = Player::new();
+        let gold = player.gold;
+
+        let gain = add_experience(&mut player, &settings, 95);
+
+        assert_eq!((gain.experience, gain.level, gain.rewards.len()), (95, 3, 2));
+        assert_eq!(player.gold, gold + 40);
+        assert!(player.is_dirty());
+
+        let gain = add_experience(&mut player, &settings, -5);
+        assert_eq!((gain.experience, player.player_experience), (0, 95));
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Alphabet the client uses to render player tags.
+const TAG_CHARS: &[u8; 14] = b"0289PYLQGRJCUV";
+
+/// A 64-bit id split into a high and a low half, as sent by the client.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LogicLong {
+    #[serde(default)]
+    pub high_id: u32,
+    pub low_id: u32,
+}
+
+impl LogicLong {
+    pub fn new(high_id: u32, low_id: u32) -> Self {
+        Self { high_id, low_id }
+    }
+
+    /// Formats the id as a `#TAG` string.
+    pub fn to_tag(self) -> String {
+        let mut id = ((self.low_id as u64) << 8) + self.high_id as u64;
+        let mut tag = Vec::new();
+
+        while id > 0 {
+            tag.push(TAG_CHARS[(id % 14) as usize]);
+            id /= 14;
+        }
+
+        tag.push(b'#');
+        tag.reverse();
+
+        String::from_utf8(tag).unwrap()
+    }
+
+    /// Parses a `#TAG` string back into an id.
+    ///
+    /// The leading `#` is optional, case is ignored and `O` is read as `0`,
+    /// since players often retype tags by hand.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim();
+        let tag = tag.strip_prefix('#').unwrap_or(tag);
+
+        if tag.is_empty() {
+            return None;
+        }
+
+        let mut id: u64 = 0;
+
+        for c in tag.bytes() {
+            let c = match c.to_ascii_uppercase() {
+                b'O' => b'0',
+                c => c,
+            };
+
+            let digit = TAG_CHARS.iter().position(|&x| x == c)?;
+            id = id.checked_mul(14)?.checked_add(digit as u64)?;
+        }
+
+        let high_id = (id & 0xff) as u32;
+        let low_id = u32::try_from(id >> 8).ok()?;
+
+        Some(Self { high_id, low_id })
+    }
+}
+
+impl fmt::Display for LogicLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_tag())
+    }
+}
+
+impl FromStr for LogicLong {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_tag(s).ok_or("invalid player tag")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_round_trip() {
+        assert_eq!(LogicLong::new(0, 1).to_tag(), "#2PP");
+
+        for id in [LogicLong::new(0, 1), LogicLong::new(3, 12345), LogicLong::new(255, u32::MAX)] {
+            assert_eq!(LogicLong::from_tag(&id.to_tag()), Some(id));
+        }
+    }
+
+    #[test]
+    fn hand_typed_tags() {
+        let id = Some(LogicLong::new(0, 1));
+
+        assert_eq!(LogicLong::from_tag("2PP"), id);
+        assert_eq!(LogicLong::from_tag(" #2pp "), id);
+        assert_eq!("#2pp".parse::<LogicLong>().ok(), id);
+        assert_eq!(LogicLong::from_tag("#2O"), LogicLong::from_tag("#20"));
+    }
+
+    #[test]
+    fn invalid_tags() {
+        assert_eq!(LogicLong::from_tag(""), None);
+        assert_eq!(LogicLong::from_tag("#"), None);
+        assert_eq!(LogicLong::from_tag("#2PA"), None);
+        assert_eq!(LogicLong::from_tag("#VVVVVVVVVVVVVVVVVVVV"), None);
+    }
+}
@@ -12,8 +12,19 @@ mod csv_logic;
 mod math;
 mod checksum;
 mod milestones;
+mod logic_long;
+mod data_ref;
+mod token;
+mod commands;
+mod reloader;
+mod render;
+mod events;
+mod ranks;
+mod experience;
+mod profile_icons;
+mod names;
+mod boxes;
 
-use config::Config;
 use log::*;
 
 use crate::logging::*;
@@ -24,8 +35,29 @@ fn main() {
     init_logging();
     let settings = Settings::load("deuce.toml").expect("deuce: failed to load settings");
 
-    info!("started server on 0.0.0.0:{}", settings.port);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
 
-    let server = Network::new(settings);
-    server.start();
+    if args.is_empty() {
+        let data = csv_logic::game_data();
+        info!("loaded {} game data tables", data.tables().count());
+
+        let problems = events::check_plan(&settings.events, &data);
+
+        if !problems.is_empty() {
+            for problem in problems {
+                error!("deuce: {}", problem);
+            }
+
+            panic!("deuce: invalid event plan");
+        }
+
+        info!("started server on 0.0.0.0:{}", settings.port);
+
+        let server = Network::new(settings);
+        server.start();
+    } else if !commands::run(&settings, &args) {
+        eprintln!("{}", commands::USAGE);
+        std::process::exit(2);
+    }
 }
@@ -0,0 +1,198 @@
+use log::*;
+
+use crate::csv_logic::game_data;
+use crate::database::{DatabaseError, Storage};
+use crate::player::Player;
+use crate::settings::Settings;
+
+/// Name every account starts with. Nobody can pick it.
+pub const DEFAULT_NAME: &str = "Brawler";
+
+/// Characters allowed besides letters and digits.
+const PUNCTUATION: &str = " _-.";
+
+/// Why a name was refused, as the client knows it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameError {
+    TooShort = 1,
+    TooLong = 2,
+    InvalidCharacters = 3,
+    Blocked = 4,
+    Taken = 5,
+    NotEnoughGems = 6,
+}
+
+/// Letters and digits of `text` in lowercase, so filtered words cannot be
+/// hidden with case, spaces or punctuation.
+pub fn normalize(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// What two names have to differ in to both be taken: anything but case.
+/// Computed here rather than in SQL so every backend folds case the same.
+pub fn key(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// Checks a name against the length, character and word rules, returning
+/// it with the surrounding spaces trimmed.
+pub fn check<'a>(name: &'a str, settings: &Settings) -> Result<&'a str, NameError> {
+    let name = name.trim();
+    let length = name.chars().count();
+
+    if length < settings.name_min_length {
+        return Err(NameError::TooShort);
+    }
+
+    if length > settings.name_max_length {
+        return Err(NameError::TooLong);
+    }
+
+    if !name.chars().all(|c| c.is_alphanumeric() || PUNCTUATION.contains(c)) || name.contains("  ") {
+        return Err(NameError::InvalidCharacters);
+    }
+
+    let normalized = normalize(name);
+
+    if normalized == normalize(DEFAULT_NAME) || settings.blocked_words.iter().any(|word| normalized.contains(word.as_str())) {
+        return Err(NameError::Blocked);
+    }
+
+    Ok(name)
+}
+
+/// Gems the player's next name change costs.
+pub fn cost(player: &Player) -> u32 {
+    if player.name_changes == 0 {
+        0
+    } else {
+        game_data().globals.name_change_cost.max(0) as u32
+    }
+}
+
+/// Renames the player if the name passes every rule and they can pay for
+/// it, returning the gems it cost.
+pub fn change(player: &mut Player, database: &dyn Storage, settings: &Settings, name: &str) -> Result<u32, NameError> {
+    let name = check(name, settings)?;
+
+    let cost = cost(player);
+
+    if player.gems < cost {
+        return Err(NameError::NotEnoughGems);
+    }
+
+    // names are reserved even when they need not be unique, so turning
+    // `unique_names` on later keeps the first holder of each name
+    match database.claim_name(player.id, name) {
+        Ok(()) => {}
+        Err(DatabaseError::AlreadyExists) if !settings.unique_names => {}
+        Err(DatabaseError::AlreadyExists) => return Err(NameError::Taken),
+        Err(e) => {
+            error!("deuce: failed to claim name {:?}: {}", name, e);
+            return Err(NameError::Taken);
+        }
+    }
+
+    info!("deuce: player {} renamed from {:?} to {:?} for {} gems", player.id, player.name, name, cost);
+
+    player.gems -= cost;
+    player.name = name.to_string();
+    player.name_changes += 1;
+    player.mark_dirty();
+
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::MemoryStorage;
+    use crate::settings::tests::parse;
+
+    fn settings(unique_names: bool) -> Settings {
+        let mut settings = parse(&format!("unique_names = {}", unique_names)).unwrap();
+        settings.blocked_words = vec!["admin".to_string()];
+
+        settings
+    }
+
+    fn player(database: &dyn Storage, token: &str, gems: u32) -> Player {
+        let mut player = Player::new();
+        player.token = Some(token.to_string());
+
+        database.create_player(&player).unwrap();
+        player.load(&database.load_player(&player).unwrap()).unwrap();
+        player.gems = gems;
+
+        player
+    }
+
+    #[test]
+    fn length_and_characters() {
+        let settings = settings(false);
+
+        assert_eq!(check("  Colt  ", &settings), Ok("Colt"));
+        assert_eq!(check("Él_Primo-2.0", &settings), Ok("Él_Primo-2.0"));
+        assert_eq!(check("C", &settings), Err(NameError::TooShort));
+        assert_eq!(check("ABCDEFGHIJKLMNOP", &settings), Err(NameError::TooLong));
+        assert_eq!(check("Bull!", &settings), Err(NameError::InvalidCharacters));
+        assert_eq!(check("Big  Bull", &settings), Err(NameError::InvalidCharacters));
+    }
+
+    #[test]
+    fn blocked_words_and_the_default_name() {
+        let settings = settings(false);
+
+        assert_eq!(check("the A.d-m_i N", &settings), Err(NameError::Blocked));
+        assert_eq!(check("BRAWLER", &settings), Err(NameError::Blocked));
+        assert_eq!(check("Brawlers", &settings), Ok("Brawlers"));
+    }
+
+    #[test]
+    fn first_change_is_free() {
+        let database = MemoryStorage::new();
+        let settings = settings(false);
+        let mut player = player(&database, "token", 40);
+
+        assert_eq!(change(&mut player, &database, &settings, "Poco"), Ok(0));
+
+        let price = game_data().globals.name_change_cost as u32;
+        assert_eq!(change(&mut player, &database, &settings, "Nita"), Ok(price));
+        assert_eq!((player.name.as_str(), player.gems, player.name_changes), ("Nita", 40 - price, 2));
+
+        player.gems = price - 1;
+        assert_eq!(change(&mut player, &database, &settings, "Jessie"), Err(NameError::NotEnoughGems));
+        assert_eq!(player.name, "Nita");
+    }
+
+    #[test]
+    fn unique_names_ignore_case() {
+        let database = MemoryStorage::new();
+        let mut first = player(&database, "first", 0);
+        let mut second = player(&database, "second", 100);
+
+        assert_eq!(change(&mut first, &database, &settings(true), "Émile"), Ok(0));
+        assert_eq!(change(&mut second, &database, &settings(true), "éMILE"), Err(NameError::Taken));
+        assert_eq!(change(&mut second, &database, &settings(false), "éMILE"), Ok(0));
+
+        // a renamed player's old name is free again
+        first.gems = 100;
+        assert!(change(&mut first, &database, &settings(true), "Leon").is_ok());
+        assert_eq!(change(&mut second, &database, &settings(true), "emile"), Ok(game_data().globals.name_change_cost as u32));
+        assert_eq!(change(&mut second, &database, &settings(true), "EMILE"), Ok(game_data().globals.name_change_cost as u32));
+    }
+
+    #[test]
+    fn names_are_reserved_before_they_must_be_unique() {
+        let database = MemoryStorage::new();
+        let mut first = player(&database, "first", 0);
+        let mut second = player(&database, "second", 0);
+
+        assert_eq!(change(&mut first, &database, &settings(false), "Piper"), Ok(0));
+        assert_eq!(change(&mut second, &database, &settings(false), "PIPER"), Ok(0));
+
+        second.gems = 100;
+        assert_eq!(change(&mut second, &database, &settings(true), "piper"), Err(NameError::Taken));
+        assert!(change(&mut second, &database, &settings(true), "Pam").is_ok());
+    }
+}
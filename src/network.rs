@@ -1,40 +1,77 @@
 use std::collections::HashMap;
-use std::io::Read;
-use std::net::{TcpListener, TcpStream};
+use std::io::{ErrorKind, Read};
+use std::net::{Shutdown, TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
 use bytes::Bytes;
 use log::*;
-use crate::database::Database;
+use crate::database::{self, DatabaseError, Storage};
 use crate::device::Device;
+use crate::events;
+use crate::logic_long::LogicLong;
+use crate::packets::packet::ServerPacket;
+use crate::csv_logic::game_data;
+use crate::packets::server::{DisconnectedMessage, DISCONNECT_CONTENT_UPDATE, DISCONNECT_LOGGED_IN_ELSEWHERE};
 use crate::packets::client::create_packet;
 use crate::player::Player;
 use crate::reader::ByteReader;
+use crate::reloader;
 use crate::settings::Settings;
 
+pub type Clients = Arc<Mutex<HashMap<String, ClientInfo>>>;
+
+/// Why a session is being closed by the server.
+pub enum Kick {
+    /// Another connection logged in as the same account. The session
+    /// signals the sender once the player has been saved.
+    Takeover(Sender<()>),
+    /// Game data was reloaded and the client needs the new content.
+    ContentUpdate,
+}
+
+impl Kick {
+    fn reason(&self) -> i32 {
+        match self {
+            Kick::Takeover(_) => DISCONNECT_LOGGED_IN_ELSEWHERE,
+            Kick::ContentUpdate => DISCONNECT_CONTENT_UPDATE,
+        }
+    }
+}
+
 pub struct ClientInfo {
-    stream: TcpStream,
+    pub stream: TcpStream,
+    /// Account logged in on this connection, if any.
+    pub account: Option<LogicLong>,
+    /// Version of the game data the session started on.
+    pub data_version: u64,
+    pub kick: Option<Kick>,
 }
 
 pub struct Network {
     settings: Arc<Settings>,
-    clients: Arc<Mutex<HashMap<String, ClientInfo>>>,
+    clients: Clients,
 
     clients_count: Arc<AtomicUsize>,
-    database: Arc<Mutex<Database>>,
+    /// Source of connection ids, never reused so a late cleanup cannot
+    /// remove a newer connection from the map.
+    next_client_id: AtomicU64,
+    database: Arc<dyn Storage>,
 }
 
 impl Network {
     pub fn new(settings: Settings) -> Self {
-        let db = Database::new(settings.database.as_str());
-        
+        let database = database::open(&settings).expect("deuce: failed to open database");
+
         Self {
             settings: Arc::new(settings),
 
             clients: Arc::new(Mutex::new(HashMap::new())),
             clients_count: Arc::new(AtomicUsize::new(0)),
+            next_client_id: AtomicU64::new(1),
             
-            database: Arc::new(Mutex::new(db)),
+            database,
         }
     }
 
@@ -42,11 +79,17 @@ impl Network {
         let listener = TcpListener::bind(format!("0.0.0.0:{}", self.settings.port))
             .expect("deuce: could not bind to 0.0.0.0");
 
-        {
-            let mut database = self.database.lock().unwrap();
-            database.create_default();
+        let version = self.database.migrate().expect("deuce: failed to migrate database");
+        info!("deuce: database schema at version {}", version);
+
+        events::start(Arc::clone(&self.database), self.settings.events.clone());
+
+        if self.settings.data_watch_interval > 0 {
+            reloader::watch(Arc::clone(&self.clients), Duration::from_secs(self.settings.data_watch_interval), self.settings.kick_on_reload);
         }
 
+        reloader::console(Arc::clone(&self.clients), self.settings.kick_on_reload);
+
         for stream in listener.incoming() {
             if stream.is_err() {
                 error!("deuce: incoming stream is erroneous, will skip");
@@ -56,25 +99,55 @@ impl Network {
             let mut stream = stream.unwrap();
 
             let count = self.clients_count.fetch_add(1, Ordering::SeqCst) + 1;
-            let client_id = count.to_string();
+            let client_id = self.next_client_id.fetch_add(1, Ordering::SeqCst).to_string();
 
             info!("deuce: client connected. total: {}", count);
 
             {
                 let mut clients = self.clients.lock().unwrap();
-                clients.insert(client_id.clone(), ClientInfo { stream: stream.try_clone().expect("deuce: failed to clone stream") });
+                clients.insert(client_id.clone(), ClientInfo {
+                    stream: stream.try_clone().expect("deuce: failed to clone stream"),
+                    account: None,
+                    data_version: game_data().version,
+                    kick: None,
+                });
             }
 
-            let mut device = Device::new(self.settings.clone(), stream.try_clone().unwrap(), Arc::clone(&self.clients));
+            let mut device = Device::new(self.settings.clone(), client_id.clone(), stream.try_clone().unwrap(), Arc::clone(&self.clients));
             let mut player = Player::new();
 
             let clients = Arc::clone(&self.clients);
             let clients_count = Arc::clone(&self.clients_count);
-            let mut database = Arc::clone(&self.database);
+            let database = Arc::clone(&self.database);
             let settings = Arc::clone(&self.settings);
 
             std::thread::spawn(move || {
+                let save_interval = Some(settings.save_interval)
+                    .filter(|&secs| secs > 0)
+                    .map(Duration::from_secs);
+
                 loop {
+                    // wait for the next packet, waking up to flush unsaved progress
+                    stream.set_read_timeout(save_interval).expect("deuce: failed to set read timeout");
+
+                    match stream.peek(&mut [0u8; 1]) {
+                        Ok(0) => break,
+                        Ok(_) => {}
+                        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                            if !save_player(&mut player, &database) {
+                                break;
+                            }
+
+                            continue;
+                        }
+                        Err(e) => {
+                            error!("deuce: failed to wait for stream: {}", e);
+                            break;
+                        }
+                    }
+
+                    stream.set_read_timeout(None).expect("deuce: failed to clear read timeout");
+
                     let mut header = [0u8; 7];
 
                     if let Err(e) = stream.read_exact(&mut header) {
@@ -103,9 +176,27 @@ impl Network {
                         if let Err(e) = packet.decode(&mut reader) {
                             error!("deuce: failed to decode packet {}: {:?}", packet_id, e);
                         } else {
-                            packet.process(&mut device, &mut player, &mut database, &settings);
+                            packet.process(&mut device, &mut player, &database, &settings);
                         }
                     }
+
+                    if !save_player(&mut player, &database) {
+                        break;
+                    }
+                }
+
+                // save before anything else, the peer may be gone already
+                save_player(&mut player, &database);
+
+                let kick = device.take_kick();
+
+                if let Some(kick) = &kick {
+                    let mut msg = DisconnectedMessage::new(kick.reason());
+                    device.send(msg.id, msg.encode(), 0);
+                }
+
+                if let Some(Kick::Takeover(ack)) = kick {
+                    let _ = ack.send(());
                 }
 
                 clients_count.fetch_sub(1, Ordering::SeqCst);
@@ -118,3 +209,42 @@ impl Network {
         }
     }
 }
+
+/// Saves the player if it has unsaved changes. Returns false when the
+/// session has to end because another session already saved newer data.
+fn save_player(player: &mut Player, database: &Arc<dyn Storage>) -> bool {
+    if !player.is_dirty() || player.token.is_none() {
+        return true;
+    }
+
+    match database.save_player(player) {
+        Ok(()) => true,
+        Err(DatabaseError::Conflict) => {
+            error!("deuce: player {} was saved by another session, dropping this one", player.id);
+            false
+        }
+        Err(e) => {
+            error!("deuce: failed to save player {}: {}", player.id, e);
+            true
+        }
+    }
+}
+
+/// Disconnects every session that started on game data older than
+/// `version`, so its client fetches the new content.
+pub fn kick_outdated(clients: &Clients, version: u64) -> usize {
+    let mut clients = clients.lock().unwrap();
+    let mut kicked = 0;
+
+    for info in clients.values_mut().filter(|info| info.data_version < version && info.kick.is_none()) {
+        info.kick = Some(Kick::ContentUpdate);
+
+        if let Err(e) = info.stream.shutdown(Shutdown::Read) {
+            error!("deuce: failed to shut down outdated session: {}", e);
+        }
+
+        kicked += 1;
+    }
+
+    kicked
+}
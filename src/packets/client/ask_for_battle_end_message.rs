@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use log::*;
+use crate::boxes;
+use crate::csv_logic::game_data;
+use crate::data_ref::{DataRef, CHARACTERS, LOCATIONS};
+use crate::database::Storage;
+use crate::device::Device;
+use crate::events;
+use crate::experience::{self, BattleResult};
+use crate::packets::commands::DeliveryCommand;
+use crate::packets::packet::{ClientPacket, ServerPacket};
+use crate::packets::server::AvailableServerCommandMessage;
+use crate::player::Player;
+use crate::ranks;
+use crate::reader::{ByteReader, DecodeError};
+use crate::settings::*;
+
+/// Showdown places that count as a win.
+const SHOWDOWN_VICTORY_RANK: i64 = 4;
+
+#[derive(Default, Debug)]
+pub struct BattlePlayer {
+    pub brawler: DataRef,
+    pub skin: DataRef,
+    pub team: i64,
+    /// Whether this is the player who sent the message.
+    pub own: bool,
+    pub name: String,
+}
+
+/// Sent by the client when a battle it played is over.
+#[derive(Default, Debug)]
+pub struct AskForBattleEndMessage {
+    /// 0 for a victory, 1 for a defeat and 2 for a draw. Not set in
+    /// showdown, which reports `rank` instead.
+    pub result: i64,
+    /// Place in showdown counting from 1, 0 in team modes.
+    pub rank: i64,
+    pub location: DataRef,
+    pub players: Vec<BattlePlayer>,
+}
+
+impl AskForBattleEndMessage {
+    /// Character id of the brawler the sender played.
+    pub fn brawler(&self) -> Option<i32> {
+        self.players.iter()
+            .find(|battler| battler.own && battler.brawler.class_id == CHARACTERS)
+            .map(|battler| battler.brawler.instance_id)
+    }
+
+    pub fn outcome(&self) -> BattleResult {
+        if self.rank > 0 {
+            return if self.rank <= SHOWDOWN_VICTORY_RANK { BattleResult::Victory } else { BattleResult::Defeat };
+        }
+
+        match self.result {
+            0 => BattleResult::Victory,
+            2 => BattleResult::Draw,
+            _ => BattleResult::Defeat,
+        }
+    }
+}
+
+impl ClientPacket for AskForBattleEndMessage {
+    fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError> {
+        self.result = stream.read_vint()?;
+        stream.read_vint()?;
+        self.rank = stream.read_vint()?;
+        self.location = stream.read_data_reference()?;
+
+        let count = stream.read_vint()?;
+
+        for _ in 0..count {
+            self.players.push(BattlePlayer {
+                brawler: stream.read_data_reference()?,
+                skin: stream.read_data_reference()?,
+                team: stream.read_vint()?,
+                own: stream.read_vint()? != 0,
+                name: stream.read_string()?,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn process(&mut self, device: &mut Device, player: &mut Player, _database: &Arc<dyn Storage>, settings: &Settings) {
+        if player.token.is_none() {
+            return;
+        }
+
+        let data = game_data();
+        let outcome = self.outcome();
+
+        let location = data.locations.get(self.location.instance_id as usize).filter(|_| self.location.class_id == LOCATIONS);
+        let multiplier = location.map_or(1, |location| events::reward_multiplier(&events::current(), &location.name, events::now()));
+        info!("deuce: player {} finished a battle in {}: {:?}, rewards x{}", player.id, location.map_or("an unknown location", |location| location.name.as_str()), outcome, multiplier);
+
+        // the client does not say who the star player was
+        let gain = experience::add_battle_result(player, settings, outcome, false, multiplier);
+
+        for (table, name) in &gain.unlocks {
+            info!("deuce: player {} unlocked {} of {} at level {}", player.id, name, table, gain.level);
+        }
+
+        gain.notify(device);
+
+        let tokens = boxes::battle_tokens(outcome, multiplier);
+        player.tokens = player.tokens.saturating_add(tokens);
+        player.mark_dirty();
+        debug!("deuce: player {} earned {} tokens", player.id, tokens);
+
+        let Some(brawler) = self.brawler() else {
+            debug!("deuce: battle of player {} names no brawler of theirs", player.id);
+            return;
+        };
+
+        let rewards = ranks::add_trophies(player, settings, brawler, ranks::battle_trophies(outcome, multiplier));
+
+        if !rewards.is_empty() {
+            let rewards: Vec<_> = rewards.iter().map(|milestone| &milestone.reward).collect();
+            let mut command = AvailableServerCommandMessage::new(Box::new(DeliveryCommand::new(&rewards)));
+            device.send(command.id, command.encode(), 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcomes() {
+        let battle = |result, rank| AskForBattleEndMessage { result, rank, ..Default::default() };
+
+        assert_eq!(battle(0, 0).outcome(), BattleResult::Victory);
+        assert_eq!(battle(1, 0).outcome(), BattleResult::Defeat);
+        assert_eq!(battle(2, 0).outcome(), BattleResult::Draw);
+        assert_eq!(battle(0, 4).outcome(), BattleResult::Victory);
+        assert_eq!(battle(0, 5).outcome(), BattleResult::Defeat);
+    }
+
+    #[test]
+    fn decode() {
+        // vints have their first byte rotated: 1 is 0x01, 15 is 0x0f
+        let mut payload = vec![0x00, 0x00, 0x01, 0x0f, 0x01, 0x01];
+        payload.extend([0x10, 0x00, 0x00, 0x00, 0x01]);
+        payload.extend([0, 0, 0, 4]);
+        payload.extend(b"Nita");
+
+        let mut message = AskForBattleEndMessage::default();
+        message.decode(&mut ByteReader::from(payload.into())).unwrap();
+
+        assert_eq!((message.result, message.rank, message.location), (0, 1, DataRef::new(15, 1)));
+        assert_eq!(message.players.len(), 1);
+        assert_eq!((message.players[0].brawler, message.players[0].skin), (DataRef::new(16, 0), DataRef::null()));
+        assert_eq!((message.players[0].own, message.players[0].name.as_str()), (true, "Nita"));
+    }
+}
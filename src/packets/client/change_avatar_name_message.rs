@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use crate::database::Storage;
+use crate::device::Device;
+use crate::names;
+use crate::packets::commands::ChangeNameCommand;
+use crate::packets::packet::{ClientPacket, ServerPacket};
+use crate::packets::server::{AvailableServerCommandMessage, AvatarNameChangeFailedMessage};
+use crate::player::Player;
+use crate::reader::{ByteReader, DecodeError};
+use crate::settings::*;
+
+#[derive(Default, Debug)]
+pub struct ChangeAvatarNameMessage {
+    pub name: String,
+}
+
+impl ClientPacket for ChangeAvatarNameMessage {
+    fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError> {
+        self.name = stream.read_string()?;
+
+        Ok(())
+    }
+
+    fn process(&mut self, device: &mut Device, player: &mut Player, database: &Arc<dyn Storage>, settings: &Settings) {
+        if player.token.is_none() {
+            return;
+        }
+
+        match names::change(player, database.as_ref(), settings, &self.name) {
+            Ok(cost) => {
+                let command = ChangeNameCommand::new(player.name.clone(), cost);
+                let mut msg = AvailableServerCommandMessage::new(Box::new(command));
+                device.send(msg.id, msg.encode(), 0);
+            }
+            Err(error) => {
+                let mut failed = AvatarNameChangeFailedMessage::new(error);
+                device.send(failed.id, failed.encode(), 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use log::*;
+use crate::database::Storage;
+use crate::device::Device;
+use crate::packets::commands::{create_command, ClientCommand};
+use crate::packets::packet::ClientPacket;
+use crate::player::Player;
+use crate::reader::{ByteReader, DecodeError};
+use crate::settings::*;
+
+#[derive(Default)]
+pub struct EndClientTurnMessage {
+    pub tick: i64,
+    pub checksum: i64,
+    pub commands: Vec<Box<dyn ClientCommand>>,
+}
+
+impl ClientPacket for EndClientTurnMessage {
+    fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError> {
+        self.tick = stream.read_vint()?;
+        self.checksum = stream.read_vint()?;
+
+        let count = stream.read_vint()?;
+
+        for _ in 0..count {
+            let command_type = stream.read_vint()?;
+
+            // commands carry no length, so nothing after an unknown one can be read
+            let Some(mut command) = create_command(command_type) else {
+                debug!("deuce: skipping the rest of a turn at unknown command {}", command_type);
+                break;
+            };
+
+            command.decode(stream)?;
+            self.commands.push(command);
+        }
+
+        Ok(())
+    }
+
+    fn process(&mut self, device: &mut Device, player: &mut Player, _database: &Arc<dyn Storage>, settings: &Settings) {
+        if player.token.is_none() {
+            return;
+        }
+
+        for command in self.commands.iter_mut() {
+            command.execute(device, player, settings);
+        }
+    }
+}
@@ -6,6 +6,9 @@ use crate::packets::{
 pub fn create_packet(packet_id: u16) -> Option<Box<dyn ClientPacket>> {
     match packet_id {
         10101 => Some(Box::new(LoginMessage::default())),
+        10212 => Some(Box::new(ChangeAvatarNameMessage::default())),
+        14102 => Some(Box::new(EndClientTurnMessage::default())),
+        14110 => Some(Box::new(AskForBattleEndMessage::default())),
         _ => None,
     }
 }
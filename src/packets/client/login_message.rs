@@ -1,17 +1,20 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use crate::packets::packet::{ClientPacket, ServerPacket};
 use crate::reader::{ByteReader, DecodeError};
 use log::*;
-use crate::database::Database;
+use crate::database::Storage;
 use crate::device::Device;
-use crate::packets::server::{LoginFailedMessage, LoginOkMessage, HomeDataMessage, MyAllianceMessage, ClanStreamMessage};
+use crate::packets::commands::DeliveryCommand;
+use crate::packets::server::{AvailableServerCommandMessage, LoginFailedMessage, LoginOkMessage, HomeDataMessage, MyAllianceMessage, ClanStreamMessage};
+use crate::logic_long::LogicLong;
 use crate::player::Player;
+use crate::ranks;
 use crate::settings::*;
+use crate::token;
 
 #[derive(Default, Debug)]
 pub struct LoginMessage {
-    pub high_id: u32,
-    pub low_id: u32,
+    pub id: LogicLong,
     pub token: String,
     pub major_version: u32,
     pub minor_version: u32,
@@ -27,8 +30,7 @@ pub struct LoginMessage {
 
 impl ClientPacket for LoginMessage {
     fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError> {
-        self.high_id = stream.read_u32()?;
-        self.low_id = stream.read_u32()?;
+        self.id = stream.read_logic_long()?;
 
         self.token = stream.read_string()?;
 
@@ -55,31 +57,98 @@ impl ClientPacket for LoginMessage {
         Ok(())
     }
 
-    fn process(&mut self, device: &mut Device, player: &mut Player, database: &mut Arc<Mutex<Database>>, settings: &Settings) {
-        let mut db = database.lock().unwrap();
+    fn process(&mut self, device: &mut Device, player: &mut Player, database: &Arc<dyn Storage>, settings: &Settings) {
+        player.region = self.region.clone();
 
-        if !db.token_exists(self.token.clone()) {
-            player.token = Some(self.token.clone());
-            player.low_id = db.get_free_id() as u32;
+        if self.token.is_empty() {
+            // fresh install, issue a token that is sent back in LoginOk
+            self.token = token::generate();
+            player.token = Some(token::hash(&self.token));
+
+            match database.create_player(player) {
+                Ok(id) => info!("deuce: created player {}", id),
+                Err(e) => {
+                    error!("deuce: failed to create player: {}", e);
+                    self.fail(device, player);
+                    return;
+                }
+            }
+        } else {
+            player.token = Some(token::hash(&self.token));
+
+            let exists = match database.token_exists(player.token.as_deref().unwrap()) {
+                Ok(exists) => exists,
+                Err(e) => {
+                    error!("deuce: failed to look up token: {}", e);
+                    self.fail(device, player);
+                    return;
+                }
+            };
+
+            if !exists {
+                info!("deuce: rejected login with unknown token");
+
+                let mut failed = LoginFailedMessage::new(self, "Unknown account. Clear the app data to start a new one.".to_string(), 1);
+                device.send(failed.id, failed.encode(), 1);
+
+                player.token = None;
+                return;
+            }
+        }
 
-            db.create_player(player).expect("deuce: failed to create player");
+        // the stored id is authoritative, the one the client sent is only a hint
+        let info = match database.load_player(player) {
+            Ok(info) => info,
+            Err(e) => {
+                error!("deuce: failed to load player: {}", e);
+                self.fail(device, player);
+                return;
+            }
+        };
+
+        if info.id != self.id {
+            debug!("deuce: client sent id {} for player {}", self.id, info.id);
         }
-        
-        player.high_id = self.high_id;
-        player.low_id = self.low_id;
-        player.token = Some(self.token.clone());
-        player.region = self.region.clone();
 
-        let info = db.load_player(player).unwrap();
+        // the old session saves before it goes away, so load again to pick up its progress
+        let info = if device.take_over(info.id) {
+            match database.load_player(player) {
+                Ok(info) => info,
+                Err(e) => {
+                    error!("deuce: failed to reload player {} after takeover: {}", info.id, e);
+                    self.fail(device, player);
+                    return;
+                }
+            }
+        } else {
+            info
+        };
+
+        info!("deuce: player {} logged in", info.id);
+
+        if let Err(e) = player.load(&info) {
+            error!("deuce: failed to load player {}: {}", info.id, e);
+            self.fail(device, player);
+            return;
+        }
 
-        let mut msg = LoginOkMessage::new(self);
+        let mut msg = LoginOkMessage::new(self, info.id);
         let encoded = msg.encode();
 
         device.send(msg.id, encoded, 1);
 
-        let mut home = HomeDataMessage::new(player, &info, &settings);
+        let mut home = HomeDataMessage::new(player, settings);
         device.send(home.id, home.encode(), 0);
 
+        // ranks reached since the last login, on top of what HomeData showed
+        let claimed = ranks::claim(player, settings);
+
+        if !claimed.is_empty() {
+            let rewards: Vec<_> = claimed.iter().map(|milestone| &milestone.reward).collect();
+            let mut command = AvailableServerCommandMessage::new(Box::new(DeliveryCommand::new(&rewards)));
+            device.send(command.id, command.encode(), 0);
+        }
+
         let mut clan = ClanStreamMessage::new();
         device.send(clan.id, clan.encode(), 0);
 
@@ -87,3 +156,14 @@ impl ClientPacket for LoginMessage {
         device.send(alliance.id, alliance.encode(), 0);
     }
 }
+
+impl LoginMessage {
+    /// Turns the client away after a server-side error, leaving the
+    /// session logged out so nothing gets saved for it.
+    fn fail(&self, device: &mut Device, player: &mut Player) {
+        player.token = None;
+
+        let mut failed = LoginFailedMessage::new(self, "Server error, try again later.".to_string(), 1);
+        device.send(failed.id, failed.encode(), 1);
+    }
+}
@@ -1,5 +1,11 @@
 mod login_message;
+mod end_client_turn_message;
+mod change_avatar_name_message;
+mod ask_for_battle_end_message;
 mod factory;
 
 pub use login_message::*;
+pub use end_client_turn_message::*;
+pub use change_avatar_name_message::*;
+pub use ask_for_battle_end_message::*;
 pub use factory::*;
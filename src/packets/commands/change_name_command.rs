@@ -0,0 +1,25 @@
+use crate::packets::commands::ServerCommand;
+use crate::writer::ByteWriter;
+
+/// Confirms a name change and the gems it cost.
+pub struct ChangeNameCommand {
+    name: String,
+    cost: u32,
+}
+
+impl ChangeNameCommand {
+    pub fn new(name: String, cost: u32) -> Self {
+        Self { name, cost }
+    }
+}
+
+impl ServerCommand for ChangeNameCommand {
+    fn command_type(&self) -> i32 {
+        201
+    }
+
+    fn encode(&self, writer: &mut ByteWriter) {
+        writer.write_string(Some(self.name.as_str()));
+        writer.write_vint(self.cost as i32);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::csv_logic::{game_data, Reward};
+use crate::data_ref::*;
+use crate::packets::commands::ServerCommand;
+use crate::writer::ByteWriter;
+
+/// Item types of a delivery besides milestone rewards, which bring their
+/// own.
+pub const ITEM_BRAWLER: i32 = 1;
+pub const ITEM_POWER_POINTS: i32 = 6;
+pub const ITEM_GOLD: i32 = 7;
+
+pub struct DeliveryItem {
+    pub item_type: i32,
+    pub amount: i32,
+    pub data: DataRef,
+}
+
+/// Hands the player things the server already granted: milestone rewards,
+/// or the contents of an opened box.
+pub struct DeliveryCommand {
+    /// Type of the box opened, 0 for plain rewards.
+    box_type: i32,
+    items: Vec<DeliveryItem>,
+}
+
+impl DeliveryCommand {
+    pub fn new(rewards: &[&Reward]) -> Self {
+        let data = game_data();
+        let resources = data.table("resources");
+
+        let items = rewards.iter()
+            .map(|reward| {
+                let resource = resources.and_then(|table| table.find(&reward.resource)).map_or(0, |row| row.id);
+
+                DeliveryItem {
+                    item_type: reward.reward_type,
+                    amount: reward.amount,
+                    data: DataRef::new(RESOURCES, resource as i32),
+                }
+            })
+            .collect();
+
+        Self { box_type: 0, items }
+    }
+
+    pub fn from_box(box_type: i32, items: Vec<DeliveryItem>) -> Self {
+        Self { box_type, items }
+    }
+}
+
+impl ServerCommand for DeliveryCommand {
+    fn command_type(&self) -> i32 {
+        203
+    }
+
+    fn encode(&self, writer: &mut ByteWriter) {
+        writer.write_vint(self.box_type);
+        writer.write_vint(self.items.len() as i32);
+
+        for item in &self.items {
+            writer.write_vint(item.item_type);
+            writer.write_vint(item.amount);
+            writer.write_data_ref(item.data);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::packets::commands::ServerCommand;
+use crate::writer::ByteWriter;
+
+/// Adds experience the player earned outside the client's own
+/// simulation, such as battle results.
+pub struct ExperienceCommand {
+    experience: i32,
+}
+
+impl ExperienceCommand {
+    pub fn new(experience: i32) -> Self {
+        Self { experience }
+    }
+}
+
+impl ServerCommand for ExperienceCommand {
+    fn command_type(&self) -> i32 {
+        206
+    }
+
+    fn encode(&self, writer: &mut ByteWriter) {
+        writer.write_vint(self.experience);
+    }
+}
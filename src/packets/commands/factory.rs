@@ -0,0 +1,9 @@
+use crate::packets::commands::*;
+
+pub fn create_command(command_type: i64) -> Option<Box<dyn ClientCommand>> {
+    match command_type {
+        500 => Some(Box::new(OpenBoxCommand::default())),
+        505 => Some(Box::new(SetProfileIconCommand::default())),
+        _ => None,
+    }
+}
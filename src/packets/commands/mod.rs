@@ -0,0 +1,46 @@
+mod change_name_command;
+mod delivery_command;
+mod experience_command;
+mod set_profile_icon_command;
+mod open_box_command;
+mod factory;
+
+pub use change_name_command::*;
+pub use delivery_command::*;
+pub use experience_command::*;
+pub use set_profile_icon_command::*;
+pub use open_box_command::*;
+pub use factory::*;
+
+use crate::device::Device;
+use crate::player::Player;
+use crate::reader::{ByteReader, DecodeError};
+use crate::settings::Settings;
+use crate::writer::ByteWriter;
+
+/// A change to the player the server made, sent in an
+/// `AvailableServerCommandMessage` so the client applies it as well.
+pub trait ServerCommand {
+    fn command_type(&self) -> i32;
+    fn encode(&self, writer: &mut ByteWriter);
+}
+
+/// Something the player did in the client, sent in an
+/// `EndClientTurnMessage`. Most are already applied by the client, so the
+/// server only has to check them and follow along; the rest are answered
+/// through the device.
+pub trait ClientCommand {
+    fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError>;
+    fn execute(&mut self, device: &mut Device, player: &mut Player, settings: &Settings);
+}
+
+/// Reads the part every client command starts with: the tick it ran at and
+/// the id of the player who ran it.
+pub fn read_command_base(stream: &mut ByteReader) -> Result<(), DecodeError> {
+    stream.read_vint()?;
+    stream.read_vint()?;
+    stream.read_vint()?;
+    stream.read_vint()?;
+
+    Ok(())
+}
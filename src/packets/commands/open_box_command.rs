@@ -0,0 +1,38 @@
+use log::*;
+
+use crate::boxes;
+use crate::device::Device;
+use crate::packets::commands::{read_command_base, ClientCommand, DeliveryCommand};
+use crate::packets::packet::ServerPacket;
+use crate::packets::server::AvailableServerCommandMessage;
+use crate::player::Player;
+use crate::reader::{ByteReader, DecodeError};
+use crate::settings::Settings;
+
+/// Buys and opens a box from the shop. The contents are rolled on the
+/// server and sent back in a delivery.
+#[derive(Default)]
+pub struct OpenBoxCommand {
+    pub box_type: i32,
+}
+
+impl ClientCommand for OpenBoxCommand {
+    fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError> {
+        read_command_base(stream)?;
+
+        self.box_type = stream.read_vint()? as i32;
+
+        Ok(())
+    }
+
+    fn execute(&mut self, device: &mut Device, player: &mut Player, _settings: &Settings) {
+        match boxes::open(player, self.box_type, &mut rand::thread_rng()) {
+            Ok(items) => {
+                let command = DeliveryCommand::from_box(self.box_type, items);
+                let mut msg = AvailableServerCommandMessage::new(Box::new(command));
+                device.send(msg.id, msg.encode(), 0);
+            }
+            Err(e) => info!("deuce: player {} cannot open box {}: {:?}", player.id, self.box_type, e),
+        }
+    }
+}
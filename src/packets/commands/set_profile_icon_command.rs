@@ -0,0 +1,39 @@
+use log::*;
+
+use crate::data_ref::*;
+use crate::device::Device;
+use crate::packets::commands::{read_command_base, ClientCommand};
+use crate::player::Player;
+use crate::profile_icons;
+use crate::reader::{ByteReader, DecodeError};
+use crate::settings::Settings;
+
+#[derive(Default)]
+pub struct SetProfileIconCommand {
+    pub icon: DataRef,
+}
+
+impl ClientCommand for SetProfileIconCommand {
+    fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError> {
+        read_command_base(stream)?;
+
+        self.icon = stream.read_data_reference()?;
+
+        Ok(())
+    }
+
+    fn execute(&mut self, _device: &mut Device, player: &mut Player, settings: &Settings) {
+        let id = self.icon.instance_id;
+
+        if self.icon.class_id != PLAYER_THUMBNAILS || id < 0 || !profile_icons::is_unlocked(player, settings, id as usize) {
+            info!(
+                "deuce: player {} picked locked profile icon {:?}, unlocked are {:?}",
+                player.id, self.icon, profile_icons::unlocked(player, settings)
+            );
+            return;
+        }
+
+        player.profile_icon = id as u32;
+        player.mark_dirty();
+    }
+}
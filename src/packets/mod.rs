@@ -1,3 +1,4 @@
 pub mod server;
 pub mod client;
+pub mod commands;
 pub mod packet;
@@ -1,5 +1,5 @@
-use std::sync::{Arc, Mutex};
-use crate::database::Database;
+use std::sync::Arc;
+use crate::database::Storage;
 use crate::device::Device;
 use crate::player::Player;
 use crate::reader::*;
@@ -7,7 +7,7 @@ use crate::settings::*;
 
 pub trait ClientPacket {
     fn decode(&mut self, stream: &mut ByteReader) -> Result<(), DecodeError>;
-    fn process(&mut self, device: &mut Device, player: &mut Player, database: &mut Arc<Mutex<Database>>, settings: &Settings);
+    fn process(&mut self, device: &mut Device, player: &mut Player, database: &Arc<dyn Storage>, settings: &Settings);
 }
 
 pub trait ServerPacket {
@@ -0,0 +1,31 @@
+use crate::packets::commands::ServerCommand;
+use crate::packets::packet::ServerPacket;
+use crate::writer::ByteWriter;
+
+pub struct AvailableServerCommandMessage {
+    pub id: u16,
+    command: Box<dyn ServerCommand>,
+}
+
+impl AvailableServerCommandMessage {
+    pub fn new(command: Box<dyn ServerCommand>) -> Self {
+        Self {
+            id: 24111,
+            command,
+        }
+    }
+}
+
+impl ServerPacket for AvailableServerCommandMessage {
+    fn encode(&mut self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+
+        writer.write_vint(self.command.command_type());
+        self.command.encode(&mut writer);
+
+        // tick the command executes at, right away
+        writer.write_vint(0);
+
+        writer.buffer
+    }
+}
@@ -0,0 +1,27 @@
+use crate::names::NameError;
+use crate::packets::packet::ServerPacket;
+use crate::writer::ByteWriter;
+
+pub struct AvatarNameChangeFailedMessage {
+    pub id: u16,
+    pub error: NameError,
+}
+
+impl AvatarNameChangeFailedMessage {
+    pub fn new(error: NameError) -> Self {
+        Self {
+            id: 20205,
+            error,
+        }
+    }
+}
+
+impl ServerPacket for AvatarNameChangeFailedMessage {
+    fn encode(&mut self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+
+        writer.write_vint(self.error as i32);
+
+        writer.buffer
+    }
+}
@@ -0,0 +1,32 @@
+use crate::packets::packet::ServerPacket;
+use crate::writer::ByteWriter;
+
+/// Reason shown when the account was logged in on another device.
+pub const DISCONNECT_LOGGED_IN_ELSEWHERE: i32 = 1;
+/// Reason shown when the server's game data changed and the client has to
+/// restart to download it.
+pub const DISCONNECT_CONTENT_UPDATE: i32 = 7;
+
+pub struct DisconnectedMessage {
+    pub id: u16,
+    pub reason: i32,
+}
+
+impl DisconnectedMessage {
+    pub fn new(reason: i32) -> Self {
+        Self {
+            id: 25892,
+            reason,
+        }
+    }
+}
+
+impl ServerPacket for DisconnectedMessage {
+    fn encode(&mut self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+
+        writer.write_int(self.reason);
+
+        writer.buffer
+    }
+}
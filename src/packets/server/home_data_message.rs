@@ -1,32 +1,32 @@
 use crate::packets::packet::ServerPacket;
 use crate::writer::ByteWriter;
 use crate::player::*;
-use crate::database::*;
 use crate::csv_logic::*;
 use crate::settings::*;
 use crate::milestones::*;
+use crate::data_ref::*;
+use crate::events::{self, Event};
 
 use std::collections::HashMap;
 
 const RESOURCE_IDS: [u16; 3] = [1, 5, 6];
-const TROPHIES_FOR_RANK: [u32; 34] = [0, 10, 20, 30, 40, 60, 80, 100, 120, 140, 160,180,220,260,300,340,380,420,460,500,550,600,650,700,750,800,850,900,950,1000,1050,1100,1150,1200];
+/// Unlock requirement the client shows for each event slot.
+const SLOT_REQUIREMENTS: [i32; 4] = [0, 3, 5, 8];
 
 pub struct HomeDataMessage<'a> {
     pub id: u16,
     
     player: &'a mut Player,
-    info: &'a PlayerInfo,
 
     settings: &'a Settings,
 }
 
 impl<'a> HomeDataMessage<'a> {
-    pub fn new(player: &'a mut Player, info: &'a PlayerInfo, settings: &'a Settings) -> Self {
+    pub fn new(player: &'a mut Player, settings: &'a Settings) -> Self {
         Self {
             id: 24101,
             
             player,
-            info,
 
             settings,
         }
@@ -37,33 +37,16 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
     fn encode(&mut self) -> Vec<u8> {
         let mut writer = ByteWriter::new();
 
-        // load player data
-        self.player.load(self.info);
-
-        // load csv data
-        let skins = Skins::get_skins();
-        let unlock_cards = Cards::get_brawlers();
-        let chars = Characters::get_brawlers();
-        let cards = Cards::get_cards();
-
         // resources
         let resources = [self.player.gold, self.player.chips, self.player.elixir];
 
         self.player.player_status = 2;
-        // TODO: replace in DB
+        self.player.mark_dirty();
 
-        let mut brawlers_trophies = 1000;
-
-        let max_rank = self.settings.max_rank;
         let max_upgrade_level = 5;
 
-        let trophies = [0,10,20,30,40,60,80,100,120,140,160,180,220,260,300,340,380,420,460,500,550,600,650,700,750,800,850,900,950,1000,1050,1100,1150,1200];
-
-        if max_rank <= 34 {
-            brawlers_trophies = trophies[(max_rank - 1) as usize];
-        } else {
-            brawlers_trophies = trophies[33] + (50 * (max_rank - 34));
-        }
+        let milestones = Milestones::new(self.settings);
+        let brawlers_trophies = milestones.max_rank_trophies();
 
         writer.write_vint(2017189);
         writer.write_vint(10);
@@ -73,7 +56,7 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
         writer.write_vint(0);
         writer.write_vint(self.player.player_experience as i32);
 
-        writer.write_sc_id(28, self.player.profile_icon as i32);
+        writer.write_data_ref(DataRef::new(PLAYER_THUMBNAILS, self.player.profile_icon as i32));
 
         writer.write_vint(7);
 
@@ -90,7 +73,7 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
         writer.write_vint(non_zero.len() as i32);
 
         for skin in non_zero {
-            writer.write_sc_id(29, skin);
+            writer.write_data_ref(DataRef::new(SKINS, skin));
         }
 
         let non_zero_skins: Vec<i32> = self.player.unlocked_brawlers
@@ -103,7 +86,7 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
         writer.write_vint(non_zero_skins.len() as i32);
 
         for skin in non_zero_skins {
-            writer.write_sc_id(29, skin);
+            writer.write_data_ref(DataRef::new(SKINS, skin));
         }
 
         writer.write_boolean(true);
@@ -124,7 +107,7 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
         writer.write_vlong(0, 1);
         writer.write_vlong(0, 1);
 
-        writer.write_sc_id(0, 1);
+        writer.write_data_ref(DataRef::null());
 
         writer.write_vint(0);
 
@@ -133,78 +116,48 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
 
         writer.write_vint(2017189);
 
-        writer.write_vint(100);
-        writer.write_vint(10);
-        writer.write_vint(80);
-        writer.write_vint(10);
-        writer.write_vint(20);
-        writer.write_vint(50);
-        writer.write_vint(50);
-        writer.write_vint(1000);
-        writer.write_vint(7 * 24);
-        writer.write_vint(brawlers_trophies as i32);
-        writer.write_vint(50);
-        writer.write_vint(9999);
-
-        writer.write_array_vint(vec![1, 2, 5, 10, 20, 60]);
-        writer.write_array_vint(vec![3, 10, 20, 60, 200, 500]);
-        writer.write_array_vint(vec![0, 30, 80, 170, 0, 0]);
-
-        writer.write_vint(self.player.event_count);
-        
-        let required = [0, 3, 5, 8];
+        let data = game_data();
+        let globals = &data.globals;
+        let (numbers, last) = globals.home_conf_numbers.split_at(HOME_CONF_NUMBERS_LEN - 2);
 
-        for event in 0..self.player.event_count {
-            writer.write_vint(event + 1);
-            writer.write_vint(required[event as usize]);
+        for number in numbers {
+            writer.write_vint(*number);
         }
 
-        writer.write_vint(self.player.event_count);
-
-        for event in 0..self.player.event_count {
-            writer.write_vint(event + 1);
-            writer.write_vint(event + 1);
+        writer.write_vint(brawlers_trophies);
 
-            writer.write_vint(1);
-            writer.write_vint(39120);
-            writer.write_vint(8);
-            writer.write_vint(8);
-            writer.write_vint(999);
+        for number in last {
+            writer.write_vint(*number);
+        }
 
-            writer.write_boolean(false);
-            writer.write_boolean(event == 4);
+        for costs in &globals.home_box_costs {
+            writer.write_array_vint(costs.clone());
+        }
 
-            writer.write_sc_id(15, 2);
+        let events: Vec<Event> = events::current().into_iter().take(self.player.event_count.max(0) as usize).collect();
+        let now = events::now();
 
-            writer.write_vint(0);
-            writer.write_vint(2);
+        writer.write_vint(events.len() as i32);
 
-            writer.write_string(Some("deuce server v1.01"));
-            writer.write_boolean(false);
+        for event in &events {
+            writer.write_vint(event.slot);
+            writer.write_vint(SLOT_REQUIREMENTS.get(event.slot as usize - 1).copied().unwrap_or(0));
         }
 
-        writer.write_vint(4);
+        writer.write_vint(events.len() as i32);
 
-        for event in 0..4 {
-            writer.write_vint(event + 1);
-            writer.write_vint(event + 1);
-
-            writer.write_vint(1337);
-            writer.write_vint(39120);
-            writer.write_vint(8);
-            writer.write_vint(8);
-            writer.write_vint(999);
-
-            writer.write_boolean(false);
-            writer.write_boolean(event == 4);
+        for event in &events {
+            let location = data.location_by_name(&event.location).map_or(0, |location| location.id);
+            write_event(&mut writer, event.slot, 1, event.time_left(now), location, &event.banner);
+        }
 
-            writer.write_sc_id(15, 3);
+        let upcoming: Vec<&Event> = events.iter().filter(|event| !event.next.is_empty()).collect();
 
-            writer.write_vint(0);
-            writer.write_vint(2);
+        writer.write_vint(upcoming.len() as i32);
 
-            writer.write_string(Some("deuce server v1.01 (2)"));
-            writer.write_boolean(false);
+        for event in upcoming {
+            let location = data.location_by_name(&event.next).map_or(0, |location| location.id);
+            write_event(&mut writer, event.slot, 1337, event.time_left(now), location, &event.banner);
         }
 
         writer.write_vint(max_upgrade_level);
@@ -213,26 +166,24 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
             writer.write_vint(x + 1);
         }
 
-        // TODO: milestones
-        let milestones = Milestones::new(self.settings);
         milestones.write_all(&mut writer);
         
-        writer.write_long(self.player.high_id as i32, self.player.low_id as i32);
+        writer.write_logic_long(self.player.id);
         writer.write_vint(0);
 
-        for id in 0..3 {
-            writer.write_vlong(self.player.high_id as i32, self.player.low_id as i32);
+        for _ in 0..3 {
+            writer.write_vlogic_long(self.player.id);
         }
 
         writer.write_string(Some(self.player.name.as_str()));
-        writer.write_boolean(self.player.name.as_str() != "Brawler");
+        writer.write_boolean(self.player.name_changes > 0);
         writer.write_int(1);
 
         writer.write_vint(5);
 
         let mut cards: HashMap<u32, u32> = HashMap::new();
 
-        for (id, brawler) in self.player.unlocked_brawlers.iter() {
+        for brawler in self.player.unlocked_brawlers.values() {
             for (&card, &amt) in brawler.cards.iter() {
                 cards.insert(card as u32, amt as u32);
             }
@@ -241,12 +192,12 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
         writer.write_vint((cards.len() + RESOURCE_IDS.len()) as i32);
 
         for (&card, &amt) in cards.iter() {
-            writer.write_sc_id(23, card as i32);
+            writer.write_data_ref(DataRef::new(CARDS, card as i32));
             writer.write_vint(amt as i32);
         }
 
         for (i, &res) in RESOURCE_IDS.iter().enumerate() {
-            writer.write_sc_id(5, res as i32);
+            writer.write_data_ref(DataRef::new(RESOURCES, res as i32));
             writer.write_vint(resources[i] as i32);
         }
 
@@ -255,14 +206,14 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
         writer.write_vint(b_count as i32);
 
         for (&id, data) in self.player.unlocked_brawlers.iter() {
-            writer.write_sc_id(16, id as i32);
+            writer.write_data_ref(DataRef::new(CHARACTERS, id));
             writer.write_vint(data.trophies as i32);
         }
 
         writer.write_vint(b_count as i32);
 
         for (&id, data) in self.player.unlocked_brawlers.iter() {
-            writer.write_sc_id(16, id as i32);
+            writer.write_data_ref(DataRef::new(CHARACTERS, id));
             writer.write_vint(data.highest_trophies as i32);
         }
 
@@ -270,8 +221,8 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
 
         writer.write_vint(b_count as i32);
 
-        for (&id, data) in self.player.unlocked_brawlers.iter() {
-            writer.write_sc_id(16, id as i32);
+        for &id in self.player.unlocked_brawlers.keys() {
+            writer.write_data_ref(DataRef::new(CHARACTERS, id));
             writer.write_vint(2);
         }
 
@@ -296,3 +247,26 @@ impl<'a> ServerPacket for HomeDataMessage<'a> {
     }
 }
 
+/// One event slot. `seconds` counts down to the end of a running event, or
+/// to the start of an upcoming one.
+fn write_event(writer: &mut ByteWriter, slot: i32, state: i32, seconds: u64, location: usize, banner: &str) {
+    writer.write_vint(slot);
+    writer.write_vint(slot);
+
+    writer.write_vint(state);
+    writer.write_vint(seconds as i32);
+    writer.write_vint(8);
+    writer.write_vint(8);
+    writer.write_vint(999);
+
+    writer.write_boolean(false);
+    writer.write_boolean(false);
+
+    writer.write_data_ref(DataRef::new(LOCATIONS, location as i32));
+
+    writer.write_vint(0);
+    writer.write_vint(2);
+
+    writer.write_string(Some(banner));
+    writer.write_boolean(false);
+}
@@ -1,17 +1,20 @@
 use crate::packets::client::LoginMessage;
 use crate::packets::packet::ServerPacket;
 use crate::writer::ByteWriter;
+use crate::logic_long::LogicLong;
 
 pub struct LoginOkMessage<'a> {
     pub id: u16,
     payload: &'a LoginMessage,
+    account_id: LogicLong,
 }
 
 impl<'a> LoginOkMessage<'a> {
-    pub fn new(payload: &'a LoginMessage) -> Self {
+    pub fn new(payload: &'a LoginMessage, account_id: LogicLong) -> Self {
         Self {
             id: 20104,
             payload,
+            account_id,
         }
     }
 }
@@ -20,8 +23,8 @@ impl<'a> ServerPacket for LoginOkMessage<'a> {
     fn encode(&mut self) -> Vec<u8> {
         let mut writer = ByteWriter::new();
         
-        writer.write_long(self.payload.high_id as i32, self.payload.low_id as i32);
-        writer.write_long(self.payload.high_id as i32, self.payload.low_id as i32);
+        writer.write_logic_long(self.account_id);
+        writer.write_logic_long(self.account_id);
 
         writer.write_string(Some(self.payload.token.as_str()));
         writer.write_string(Some("467606826913688"));
@@ -3,10 +3,16 @@ mod login_ok_message;
 mod home_data_message;
 mod clan_stream_message;
 mod my_alliance_message;
+mod disconnected_message;
+mod available_server_command_message;
+mod avatar_name_change_failed_message;
 
 pub use login_failed_message::*;
 pub use login_ok_message::*;
 pub use home_data_message::*;
 pub use clan_stream_message::*;
 pub use my_alliance_message::*;
+pub use disconnected_message::*;
+pub use available_server_command_message::*;
+pub use avatar_name_change_failed_message::*;
 
@@ -52,7 +52,10 @@ high_id = 0
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::csv_logic::Reward;
 use crate::database::PlayerInfo;
+use crate::logic_long::LogicLong;
+use crate::names::DEFAULT_NAME;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BrawlerData {
@@ -65,13 +68,19 @@ pub struct BrawlerData {
     pub power_points: i32,
     pub state: i32,
     pub star_power: i32,
+    /// Highest rank whose reward was granted.
+    #[serde(default = "first_rank")]
+    pub claimed_rank: i32,
+}
+
+fn first_rank() -> i32 {
+    1
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Player {
     pub name: String,
-    pub low_id: u32,
-    pub high_id: u32,
+    pub id: LogicLong,
     pub player_experience: u32,
     pub solo_wins: u32,
     pub duo_wins: u32,
@@ -82,6 +91,9 @@ pub struct Player {
     pub chips: u32,
     pub coins_doubler: u32,
     pub coins_booster: u32,
+    /// Tokens earned in battles, spent on brawl boxes.
+    #[serde(default)]
+    pub tokens: u32,
     pub trophies: u32,
     pub highest_trophies: u32,
     pub profile_icon: u32,
@@ -93,18 +105,29 @@ pub struct Player {
     pub has_battle_hints: bool,
     pub coins_reward: i32,
     pub event_count: i32,
+    /// Times the player picked a name, the first one is free.
+    #[serde(default)]
+    pub name_changes: i32,
+    /// Boxes opened since the last brawler of each rarity.
+    #[serde(default)]
+    pub box_pity: HashMap<String, i32>,
 
     pub token: Option<String>,
     pub version: u16,
     pub unlocked_brawlers: HashMap<i32, BrawlerData>,
+
+    /// Storage revision this copy was loaded at, checked when saving.
+    #[serde(skip)]
+    pub revision: i64,
+    #[serde(skip)]
+    dirty: bool,
 }
 
 impl Player {
     pub fn new() -> Self {
         Self {
-            name: "Brawler".to_string(),
-            low_id: 1,
-            high_id: 0,
+            name: DEFAULT_NAME.to_string(),
+            id: LogicLong::new(0, 1),
             player_experience: 0,
             solo_wins: 0,
             duo_wins: 0,
@@ -115,6 +138,7 @@ impl Player {
             chips: 0,
             coins_doubler: 0,
             coins_booster: 0,
+            tokens: 0,
             trophies: 0,
             highest_trophies: 0,
             profile_icon: 0,
@@ -126,18 +150,23 @@ impl Player {
             has_battle_hints: false,
             coins_reward: 0,
             event_count: 4,
+            name_changes: 0,
+            box_pity: HashMap::new(),
 
             token: None,
             version: 1,
             unlocked_brawlers: HashMap::new(),
+
+            revision: 0,
+            dirty: false,
         }
     }
 
     pub fn load(&mut self, info: &PlayerInfo) -> Result<(), Box<dyn std::error::Error>> {
-        let token = self.token.as_ref().ok_or("deuce: cannot load when token is None");
-        
+        self.token.as_ref().ok_or("deuce: cannot load when token is None")?;
+
         self.name = info.name.clone();
-        self.low_id = info.low_id;
+        self.id = info.id;
         self.player_experience = info.player_experience;
         self.solo_wins = info.solo_wins;
         self.duo_wins = info.duo_wins;
@@ -148,9 +177,10 @@ impl Player {
         self.chips = info.chips;
         self.coins_doubler = info.coins_doubler;
         self.coins_booster = info.coins_booster;
+        self.tokens = info.tokens;
         self.trophies = info.trophies;
         self.highest_trophies = info.highest_trophies;
-        self.profile_icon = 0;
+        self.profile_icon = info.profile_icon;
         self.room_id = info.room_id;
         self.last_connection_time = info.last_connection_time;
         self.player_status = info.player_status;
@@ -160,9 +190,66 @@ impl Player {
         self.unlocked_brawlers = info.unlocked_brawlers.clone();
         self.coins_reward = info.coins_reward;
         self.event_count = info.event_count;
+        self.name_changes = info.name_changes;
+        self.box_pity = info.box_pity.clone();
+
+        self.revision = info.revision;
+        self.dirty = false;
 
         Ok(())
     }
+
+    /// Adds a reward to the player's resources. Returns false for resources
+    /// the server does not keep.
+    pub fn grant(&mut self, reward: &Reward) -> bool {
+        let resource = match reward.resource.as_str() {
+            "Diamonds" => &mut self.gems,
+            "Gold" => &mut self.gold,
+            "Dust" => &mut self.chips,
+            "Upgradium" => &mut self.elixir,
+            _ => return false,
+        };
+
+        *resource = resource.saturating_add_signed(reward.amount);
+        self.mark_dirty();
+
+        true
+    }
+
+    /// Takes what something costs from the player, by the resource names
+    /// of boxes.csv. Returns false if they cannot pay.
+    pub fn spend(&mut self, resource: &str, amount: u32) -> bool {
+        let balance = match resource {
+            "Gold" => &mut self.gold,
+            "Diamonds" => &mut self.gems,
+            "Tokens" => &mut self.tokens,
+            _ => return false,
+        };
+
+        if *balance < amount {
+            return false;
+        }
+
+        *balance -= amount;
+        self.mark_dirty();
+
+        true
+    }
+
+    /// Flags the player as changed so the session saves it.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Records a successful save at the given storage revision.
+    pub fn load_revision(&mut self, revision: i64) {
+        self.revision = revision;
+        self.dirty = false;
+    }
 }
 
 /*
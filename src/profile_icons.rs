@@ -0,0 +1,33 @@
+use crate::csv_logic::{game_data, GameData, ThumbnailRow};
+use crate::experience;
+use crate::player::Player;
+use crate::settings::Settings;
+
+/// Whether the player meets every requirement of an icon in
+/// player_thumbnails.csv.
+fn meets(data: &GameData, player: &Player, level: i32, thumbnail: &ThumbnailRow) -> bool {
+    let has_hero = thumbnail.required_hero.is_empty() || data.character_by_name(&thumbnail.required_hero)
+        .is_some_and(|character| player.unlocked_brawlers.contains_key(&(character.id as i32)));
+
+    level >= thumbnail.required_exp_level
+        && player.highest_trophies as i32 >= thumbnail.required_total_trophies
+        && has_hero
+}
+
+/// Ids of the icons the player has unlocked.
+pub fn unlocked(player: &Player, settings: &Settings) -> Vec<usize> {
+    let data = game_data();
+    let level = experience::level(player.player_experience, settings);
+
+    data.thumbnails.iter()
+        .filter(|thumbnail| meets(&data, player, level, thumbnail))
+        .map(|thumbnail| thumbnail.id)
+        .collect()
+}
+
+pub fn is_unlocked(player: &Player, settings: &Settings, id: usize) -> bool {
+    let data = game_data();
+    let level = experience::level(player.player_experience, settings);
+
+    data.thumbnails.get(id).is_some_and(|thumbnail| meets(&data, player, level, thumbnail))
+}
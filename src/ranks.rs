@@ -0,0 +1,197 @@
+use log::*;
+
+use crate::csv_logic::{game_data, Milestone, BRAWLER_RANK, TROPHY_ROAD};
+use crate::experience::BattleResult;
+use crate::milestones::Milestones;
+use crate::player::Player;
+use crate::settings::Settings;
+
+/// Trophies a battle is worth to the brawler played, from globals.csv.
+/// Event multipliers only scale gains, never losses.
+pub fn battle_trophies(result: BattleResult, multiplier: u32) -> i32 {
+    let globals = &game_data().globals;
+
+    let trophies = match result {
+        BattleResult::Victory => globals.trophies_for_result[0],
+        BattleResult::Draw => globals.trophies_for_result[1],
+        BattleResult::Defeat => globals.trophies_for_result[2],
+    };
+
+    if trophies > 0 { trophies.saturating_mul(i32::try_from(multiplier).unwrap_or(i32::MAX)) } else { trophies }
+}
+
+/// Rank a brawler with `trophies` has, counting from 1 and capped at
+/// `max_rank`.
+pub fn rank(trophies: i32, settings: &Settings) -> i32 {
+    let data = game_data();
+
+    data.milestone_track(BRAWLER_RANK).map_or(1, |track| {
+        1 + track.level(trophies, track.step_count(settings.max_rank)) as i32
+    })
+}
+
+/// Grants the rewards of every rank a brawler reached but has not claimed
+/// yet, going by its highest trophies, and returns those ranks' steps.
+pub fn claim(player: &mut Player, settings: &Settings) -> Vec<Milestone> {
+    let data = game_data();
+
+    let Some(track) = data.milestone_track(BRAWLER_RANK) else {
+        return Vec::new();
+    };
+
+    let mut ids: Vec<i32> = player.unlocked_brawlers.keys().copied().collect();
+    ids.sort_unstable();
+
+    let mut claimed = Vec::new();
+
+    for id in ids {
+        let brawler = &player.unlocked_brawlers[&id];
+        let (from, to) = (brawler.claimed_rank.max(1), rank(brawler.highest_trophies, settings));
+
+        if to <= from {
+            continue;
+        }
+
+        // reaching rank n completes step n - 2
+        for milestone in (from - 1..to - 1).map(|index| track.milestone(index as usize)) {
+            if !player.grant(&milestone.reward) {
+                warn!("deuce: cannot grant {} {} for rank {} of brawler {}", milestone.reward.amount, milestone.reward.resource, milestone.index + 2, id);
+            }
+
+            claimed.push(milestone);
+        }
+
+        player.unlocked_brawlers.get_mut(&id).unwrap().claimed_rank = to;
+        player.mark_dirty();
+
+        info!("deuce: player {} brawler {} reached rank {}", player.id, id, to);
+    }
+
+    claimed
+}
+
+/// Applies a trophy change to a brawler and the player's totals, then
+/// grants the trophy road and rank rewards it reached. Returns the steps
+/// granted.
+pub fn add_trophies(player: &mut Player, settings: &Settings, brawler: i32, delta: i32) -> Vec<Milestone> {
+    let Some(data) = player.unlocked_brawlers.get_mut(&brawler) else {
+        return Vec::new();
+    };
+
+    // a brawler cannot drop below 0, and the total only moves as far as it did
+    let old = data.trophies;
+    data.trophies = (data.trophies + delta).max(0);
+    data.highest_trophies = data.highest_trophies.max(data.trophies);
+
+    let delta = data.trophies - old;
+    let highest = player.highest_trophies as i32;
+
+    player.trophies = player.trophies.saturating_add_signed(delta);
+    player.highest_trophies = player.highest_trophies.max(player.trophies);
+    player.mark_dirty();
+
+    let mut granted = Milestones::new(settings).grant(player, TROPHY_ROAD, highest, player.highest_trophies as i32);
+    granted.extend(claim(player, settings));
+
+    granted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+    use crate::database::{MemoryStorage, Storage};
+    use crate::player::BrawlerData;
+    use crate::settings::tests::parse;
+
+    fn brawler(trophies: i32) -> BrawlerData {
+        BrawlerData {
+            cards: HashMap::from([(0, 1)]),
+            skins: vec![0],
+            selected: 0,
+            trophies,
+            highest_trophies: trophies,
+            level: 0,
+            power_points: 0,
+            state: 2,
+            star_power: 0,
+            claimed_rank: 1,
+        }
+    }
+
+    #[test]
+    fn multipliers_scale_only_gains() {
+        let globals = &game_data().globals;
+
+        assert_eq!(battle_trophies(BattleResult::Victory, 1), globals.trophies_for_result[0]);
+        assert_eq!(battle_trophies(BattleResult::Victory, 3), globals.trophies_for_result[0] * 3);
+        assert_eq!(battle_trophies(BattleResult::Defeat, 3), globals.trophies_for_result[2]);
+        assert_eq!(battle_trophies(BattleResult::Victory, u32::MAX), i32::MAX);
+    }
+
+    #[test]
+    fn ranks_follow_thresholds() {
+        let settings = parse("").unwrap();
+
+        assert_eq!(rank(0, &settings), 1);
+        assert_eq!(rank(9, &settings), 1);
+        assert_eq!(rank(10, &settings), 2);
+        assert_eq!(rank(25, &settings), 3);
+        assert_eq!(rank(1_000_000, &settings), settings.max_rank as i32);
+    }
+
+    #[test]
+    fn ranks_are_claimed_once() {
+        let settings = parse("").unwrap();
+        let mut player = Player::new();
+        player.unlocked_brawlers.insert(0, brawler(25));
+        player.unlocked_brawlers.insert(1, brawler(5));
+        let gold = player.gold;
+
+        let claimed = claim(&mut player, &settings);
+
+        // ranks 2 and 3 of the first brawler, nothing for the second
+        assert_eq!(claimed.iter().map(|milestone| milestone.index).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(player.gold, gold + 20);
+        assert_eq!((player.unlocked_brawlers[&0].claimed_rank, player.unlocked_brawlers[&1].claimed_rank), (3, 1));
+        assert!(claim(&mut player, &settings).is_empty());
+    }
+
+    #[test]
+    fn claimed_ranks_survive_a_reload() {
+        let database = MemoryStorage::new();
+        let settings = parse("").unwrap();
+
+        let mut player = Player::new();
+        player.token = Some("token".to_string());
+        database.create_player(&player).unwrap();
+        player.load(&database.load_player(&player).unwrap()).unwrap();
+        player.unlocked_brawlers.insert(0, brawler(0));
+
+        assert_eq!(add_trophies(&mut player, &settings, 0, 12).len(), 1);
+        database.save_player(&mut player).unwrap();
+
+        let mut reloaded = Player::new();
+        reloaded.token = player.token.clone();
+        reloaded.load(&database.load_player(&reloaded).unwrap()).unwrap();
+
+        assert_eq!(reloaded.unlocked_brawlers[&0].claimed_rank, 2);
+        assert!(claim(&mut reloaded, &settings).is_empty());
+        assert_eq!(reloaded.gold, player.gold);
+    }
+
+    #[test]
+    fn trophies_stop_at_zero() {
+        let settings = parse("").unwrap();
+        let mut player = Player::new();
+        player.unlocked_brawlers.insert(0, brawler(3));
+        player.unlocked_brawlers.insert(1, brawler(20));
+        player.trophies = 23;
+
+        add_trophies(&mut player, &settings, 0, -8);
+
+        assert_eq!((player.unlocked_brawlers[&0].trophies, player.trophies), (0, 20));
+        assert!(add_trophies(&mut player, &settings, 2, 8).is_empty());
+        assert_eq!(player.trophies, 20);
+    }
+}
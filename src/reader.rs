@@ -1,6 +1,8 @@
 use bytes::{Buf, Bytes};
 use std::io;
 use std::string::FromUtf8Error;
+use crate::data_ref::DataRef;
+use crate::logic_long::LogicLong;
 
 #[derive(Debug)]
 pub enum DecodeError {
@@ -70,6 +72,12 @@ impl ByteReader {
         Ok((high, low))
     }
 
+    pub fn read_logic_long(&mut self) -> Result<LogicLong, DecodeError> {
+        let (high, low) = self.read_long()?;
+
+        Ok(LogicLong::new(high, low))
+    }
+
     pub fn read_variable_int(&mut self, rotate: bool) -> Result<u64, DecodeError> {
         let mut result: u64 = 0;
         let mut shift = 0;
@@ -118,14 +126,14 @@ impl ByteReader {
         Ok(String::from_utf8(bytes.to_vec())?)
     }
 
-    pub fn read_data_reference(&mut self) -> Result<(i64, i64), DecodeError> {
-        let high = self.read_vint()?;
+    pub fn read_data_reference(&mut self) -> Result<DataRef, DecodeError> {
+        let high = self.read_vint()? as i32;
 
         if high == 0 {
-            Ok((high, 0))
+            Ok(DataRef::null())
         } else {
-            let low = self.read_vint()?;
-            Ok((high, low))
+            let low = self.read_vint()? as i32;
+            Ok(DataRef::new(high, low))
         }
     }
 
@@ -158,3 +166,37 @@ impl ByteReader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(bytes: &[u8]) -> ByteReader {
+        ByteReader::from(Bytes::copy_from_slice(bytes))
+    }
+
+    #[test]
+    fn vints() {
+        // the first byte has its sign and low bits rotated
+        assert_eq!(reader(&[0x00]).read_vint().unwrap(), 0);
+        assert_eq!(reader(&[0x05]).read_vint().unwrap(), 5);
+        assert_eq!(reader(&[0x40]).read_vint().unwrap(), -1);
+        assert_eq!(reader(&[0x80, 0x01]).read_vint().unwrap(), 64);
+    }
+
+    #[test]
+    fn data_references() {
+        let mut stream = reader(&[0x10, 0x05, 0x00]);
+
+        assert_eq!(stream.read_data_reference().unwrap(), DataRef::new(16, 5));
+        assert_eq!(stream.read_data_reference().unwrap(), DataRef::null());
+        assert!(matches!(stream.read_data_reference(), Err(DecodeError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn logic_longs() {
+        let mut stream = reader(&[0, 0, 0, 1, 0, 0, 0, 2]);
+
+        assert_eq!(stream.read_logic_long().unwrap(), LogicLong::new(1, 2));
+    }
+}
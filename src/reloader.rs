@@ -0,0 +1,71 @@
+use std::io::BufRead;
+use std::time::{Duration, SystemTime};
+use log::*;
+
+use crate::csv_logic::{reload_game_data, DATA_DIR};
+use crate::network::{kick_outdated, Clients};
+
+/// Reloads the game data. Running sessions pick the new tables up with
+/// their next request, unless `kick` is set, in which case the sessions
+/// started on older tables are disconnected so their clients fetch the new
+/// content. Broken data is logged and the old tables are kept.
+pub fn reload(clients: &Clients, kick: bool) {
+    match reload_game_data() {
+        Ok(data) if kick => {
+            let kicked = kick_outdated(clients, data.version);
+            info!("deuce: reloaded game data (version {}), {} sessions need a content update", data.version, kicked);
+        }
+        Ok(data) => info!("deuce: reloaded game data (version {})", data.version),
+        Err(e) => error!("deuce: game data not reloaded, keeping the old tables: {}", e),
+    }
+}
+
+/// Number of files and the newest modification time in the data directory.
+fn stamp() -> Option<(usize, SystemTime)> {
+    let mut count = 0;
+    let mut newest = SystemTime::UNIX_EPOCH;
+
+    for entry in std::fs::read_dir(DATA_DIR).ok()? {
+        let modified = entry.ok()?.metadata().ok()?.modified().ok()?;
+
+        count += 1;
+        newest = newest.max(modified);
+    }
+
+    Some((count, newest))
+}
+
+/// Polls the data directory and reloads whenever a file in it changes.
+pub fn watch(clients: Clients, interval: Duration, kick: bool) {
+    std::thread::spawn(move || {
+        let mut last = stamp();
+
+        loop {
+            std::thread::sleep(interval);
+
+            let current = stamp();
+
+            if current != last {
+                info!("deuce: game data changed on disk, reloading");
+
+                last = current;
+                reload(&clients, kick);
+            }
+        }
+    });
+}
+
+/// Reads admin commands from the server's standard input.
+pub fn console(clients: Clients, kick: bool) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+
+            match line.trim() {
+                "" => {}
+                "reload" => reload(&clients, kick),
+                other => warn!("deuce: unknown console command {}, try: reload", other),
+            }
+        }
+    });
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+
+use crate::csv_logic::{GameData, Map, Marker, TileRow};
+
+/// Pixels per tile in the PNG and SVG output.
+const CELL: usize = 16;
+
+const SPAWN_COLORS: [[u8; 3]; 2] = [[40, 90, 230], [220, 50, 50]];
+const OBJECTIVE_COLOR: [u8; 3] = [250, 200, 40];
+
+/// Color of a tile, picked from what it does in tiles.csv.
+fn tile_color(tile: &TileRow) -> [u8; 3] {
+    match tile {
+        tile if tile.hides_hero && tile.respawn_seconds > 0 => [120, 195, 95],
+        tile if tile.hides_hero => [60, 150, 60],
+        tile if tile.blocks_movement && !tile.blocks_projectiles => [70, 130, 210],
+        tile if tile.blocks_movement && !tile.is_destructible => [80, 80, 90],
+        tile if tile.blocks_movement && tile.respawn_seconds > 0 => [150, 170, 110],
+        tile if tile.blocks_movement => [160, 110, 70],
+        tile if tile.code != '.' => [210, 185, 135],
+        _ => [235, 215, 165],
+    }
+}
+
+fn marker_color(marker: Marker) -> [u8; 3] {
+    match marker {
+        Marker::Spawn(team) => SPAWN_COLORS[team as usize - 1],
+        Marker::Objective(_) => OBJECTIVE_COLOR,
+    }
+}
+
+fn marker_name(marker: Marker) -> String {
+    match marker {
+        Marker::Spawn(team) => format!("spawn of team {}", team),
+        Marker::Objective(digit) => format!("objective {}", digit),
+    }
+}
+
+/// The map as text with a column ruler, row numbers and a legend of the
+/// tiles and markers on it.
+pub fn ascii(map: &Map, data: &GameData) -> String {
+    let mut out = String::new();
+    let digit = |n: usize| char::from_digit((n % 10) as u32, 10).unwrap();
+    let tens: String = (0..map.width).map(|x| if x % 10 == 0 { digit(x / 10) } else { ' ' }).collect();
+    let units: String = (0..map.width).map(digit).collect();
+
+    writeln!(out, "{} ({}x{}, maps.csv line {})", map.name, map.width, map.height, map.line).unwrap();
+    writeln!(out, "    {}", tens).unwrap();
+    writeln!(out, "    {}", units).unwrap();
+
+    for y in 0..map.height {
+        let line: String = (0..map.width).filter_map(|x| map.cell(x, y)).map(|cell| cell.code).collect();
+        writeln!(out, "{:>3} {}", y, line).unwrap();
+    }
+
+    let mut codes = BTreeMap::new();
+    let mut markers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (x, y, cell) in map.cells() {
+        match cell.marker {
+            Some(marker) => markers.entry(marker_name(marker)).or_default().push(format!("{},{}", x, y)),
+            None => *codes.entry(cell.code).or_insert(0) += 1,
+        }
+    }
+
+    writeln!(out).unwrap();
+
+    for (code, count) in codes {
+        let name = data.tile_by_code(code).map_or("unknown tile", |tile| tile.name.as_str());
+        writeln!(out, "  {}  {:<30} {} tiles", code, name, count).unwrap();
+    }
+
+    for (name, positions) in markers {
+        writeln!(out, "  {:<33} {}", name, positions.join(" ")).unwrap();
+    }
+
+    out
+}
+
+pub fn svg(map: &Map, data: &GameData) -> String {
+    let hex = |[r, g, b]: [u8; 3]| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = map.width * CELL,
+        h = map.height * CELL
+    ).unwrap();
+    writeln!(out, "<title>{}</title>", map.name).unwrap();
+
+    for (x, y, cell) in map.cells() {
+        let tile = &data.tiles[cell.tile];
+        writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{c}" height="{c}" fill="{}"><title>{} {},{}</title></rect>"#,
+            x * CELL, y * CELL, hex(tile_color(tile)), tile.name, x, y, c = CELL
+        ).unwrap();
+    }
+
+    for (x, y, marker) in map.markers() {
+        let (cx, cy) = (x * CELL + CELL / 2, y * CELL + CELL / 2);
+        // where the marker ends up in a battle, for placing things by hand
+        let (wx, wy) = Map::position(x, y);
+        let label = match marker {
+            Marker::Spawn(digit) | Marker::Objective(digit) => digit,
+        };
+
+        writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="black"><title>{} {},{} (world {},{})</title></circle>"#,
+            cx, cy, CELL / 2 - 2, hex(marker_color(marker)), marker_name(marker), x, y, wx, wy
+        ).unwrap();
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+            cx, cy, CELL * 3 / 4, label
+        ).unwrap();
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+pub fn png<W: io::Write>(map: &Map, data: &GameData, writer: W) -> Result<(), png::EncodingError> {
+    let (width, height) = (map.width * CELL, map.height * CELL);
+    let mut pixels = vec![0; width * height * 3];
+
+    let mut fill = |x: usize, y: usize, inset: usize, color: [u8; 3]| {
+        for py in y * CELL + inset..(y + 1) * CELL - inset {
+            for px in x * CELL + inset..(x + 1) * CELL - inset {
+                let i = (py * width + px) * 3;
+                pixels[i..i + 3].copy_from_slice(&color);
+            }
+        }
+    };
+
+    for (x, y, cell) in map.cells() {
+        fill(x, y, 0, tile_color(&data.tiles[cell.tile]));
+    }
+
+    for (x, y, marker) in map.markers() {
+        fill(x, y, 2, [0, 0, 0]);
+        fill(x, y, 3, marker_color(marker));
+    }
+
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder.write_header()?.write_image_data(&pixels)
+}
@@ -1,5 +1,8 @@
 use config::Config;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::names;
 
 #[derive(Deserialize)]
 pub struct Settings {
@@ -7,17 +10,240 @@ pub struct Settings {
     pub port: u32,
     pub database: String,
     pub max_rank: u32,
+    /// Seconds between periodic saves of a session's unsaved progress, 0
+    /// to only save after each packet.
+    #[serde(default = "default_save_interval")]
+    pub save_interval: u64,
+    /// Maximum number of open Postgres connections.
+    #[serde(default = "default_database_pool_size")]
+    pub database_pool_size: u32,
+    /// Seconds to wait for a Postgres connection before giving up.
+    #[serde(default = "default_database_connect_timeout")]
+    pub database_connect_timeout: u64,
+    /// Postgres TLS mode: "disable", "prefer" or "require".
+    #[serde(default)]
+    pub database_tls: DatabaseTls,
+    /// Seconds between checks of the game data for changes, 0 to only
+    /// reload from the console.
+    #[serde(default = "default_data_watch_interval")]
+    pub data_watch_interval: u64,
+    /// Whether reloading game data disconnects the sessions started on the
+    /// old tables, for changes the client has to download. Turned off, they
+    /// keep going and use the new tables from their next request.
+    #[serde(default = "default_kick_on_reload")]
+    pub kick_on_reload: bool,
+    /// TOML or JSON file planning the event slots, the default rotation
+    /// when unset.
+    #[serde(default)]
+    pub event_plan: Option<String>,
+    #[serde(skip)]
+    pub events: EventPlan,
+    /// Shortest and longest names players can pick, in characters.
+    #[serde(default = "default_name_min_length")]
+    pub name_min_length: usize,
+    #[serde(default = "default_name_max_length")]
+    pub name_max_length: usize,
+    /// Whether each name can be held by one player only. Names are reserved
+    /// either way, whoever took a name first keeps it once this is turned on.
+    #[serde(default)]
+    pub unique_names: bool,
+    /// File of words players cannot use in their names, see `names.txt`.
+    #[serde(default)]
+    pub name_filter: Option<String>,
+    /// Words of `name_filter`, normalized like names are checked.
+    #[serde(skip)]
+    pub blocked_words: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseTls {
+    #[default]
+    Disable,
+    Prefer,
+    Require,
+}
+
+/// Event slots and special events, see `events.toml`.
+#[derive(Deserialize, Clone)]
+pub struct EventPlan {
+    /// Text on slots that do not set their own banner.
+    #[serde(default = "default_banner")]
+    pub banner: String,
+    /// Regular slots shown on the home screen, in order.
+    #[serde(default = "default_event_slots")]
+    pub slots: Vec<EventSlot>,
+    /// Rotations that take over a slot for a while.
+    #[serde(default)]
+    pub specials: Vec<SpecialEvent>,
+}
+
+impl Default for EventPlan {
+    fn default() -> Self {
+        Self { banner: default_banner(), slots: default_event_slots(), specials: Vec::new() }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct EventSlot {
+    /// Game modes of locations.csv the slot rotates through.
+    #[serde(default)]
+    pub modes: Vec<String>,
+    /// Names in locations.csv to rotate through in this order, instead of
+    /// every location of `modes`.
+    #[serde(default)]
+    pub locations: Vec<String>,
+    /// Seconds each location stays up before the next one.
+    pub duration: u64,
+    #[serde(default)]
+    pub banner: Option<String>,
+    #[serde(default = "default_reward_multiplier")]
+    pub reward_multiplier: u32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SpecialEvent {
+    /// Slot taken over, counting from 1. A slot past the regular ones is
+    /// only shown while the special runs.
+    pub slot: usize,
+    /// Unix time the special starts at.
+    pub start: u64,
+    /// Unix time the slot goes back to its regular rotation.
+    pub end: u64,
+    #[serde(flatten)]
+    pub rotation: EventSlot,
+}
+
+fn default_save_interval() -> u64 {
+    60
+}
+
+fn default_database_pool_size() -> u32 {
+    16
+}
+
+fn default_database_connect_timeout() -> u64 {
+    10
+}
+
+fn default_data_watch_interval() -> u64 {
+    5
+}
+
+fn default_kick_on_reload() -> bool {
+    true
+}
+
+fn default_banner() -> String {
+    "deuce server v1.01".to_string()
+}
+
+fn default_event_slots() -> Vec<EventSlot> {
+    let slot = |modes: &[&str]| EventSlot {
+        modes: modes.iter().map(|mode| mode.to_string()).collect(),
+        locations: Vec::new(),
+        duration: 86400,
+        banner: None,
+        reward_multiplier: 1,
+    };
+
+    vec![
+        slot(&["CoinRush"]),
+        slot(&["BattleRoyale"]),
+        slot(&["BountyHunter", "AttackDefend"]),
+        slot(&["LaserBall", "BossFight"]),
+    ]
+}
+
+fn default_reward_multiplier() -> u32 {
+    1
+}
+
+fn default_name_min_length() -> usize {
+    2
+}
+
+fn default_name_max_length() -> usize {
+    15
 }
 
 impl Settings {
     pub fn load(file: &'static str) -> Result<Self, String> {
-        let configuration = Config::builder()
-            .add_source(config::File::with_name(file))
-            .build();
+        let mut settings: Self = read(file)?;
 
-        match configuration {
-            Ok(config) => config.try_deserialize().map_err(|e| e.to_string()),
-            Err(e) => Err(e.to_string()),
+        if let Some(plan) = &settings.event_plan {
+            settings.events = read(plan)?;
         }
+
+        if let Some(filter) = &settings.name_filter {
+            settings.blocked_words = read_words(filter)?;
+        }
+
+        Ok(settings)
+    }
+}
+
+fn read<T: DeserializeOwned>(file: &str) -> Result<T, String> {
+    let configuration = Config::builder()
+        .add_source(config::File::with_name(file))
+        .build();
+
+    match configuration {
+        Ok(config) => config.try_deserialize().map_err(|e| format!("{}: {}", file, e)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Reads a word list: one word per line with `#` comments. Words are
+/// normalized, and the ones left empty dropped since they would block
+/// every name.
+fn read_words(file: &str) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+
+    Ok(parse_words(&text))
+}
+
+fn parse_words(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .map(names::normalize)
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Settings as written in a TOML string, on top of the required keys.
+    pub fn parse(extra: &str) -> Result<Settings, String> {
+        let text = format!("key = \"\"\ndatabase = \"memory://\"\nport = 9339\nmax_rank = 20\n{}", extra);
+
+        Config::builder()
+            .add_source(config::File::from_str(&text, config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn word_lists() {
+        let words = parse_words("# comment\nAdmin\n\n  Super Cell \n...\n-\n");
+
+        assert_eq!(words, ["admin", "supercell"]);
+    }
+
+    #[test]
+    fn database_tls_modes() {
+        assert!(matches!(parse("").unwrap().database_tls, DatabaseTls::Disable));
+        assert!(matches!(parse("database_tls = \"require\"").unwrap().database_tls, DatabaseTls::Require));
+        assert!(parse("database_tls = \"always\"").is_err());
+    }
+
+    #[test]
+    fn reloads_kick_outdated_sessions_unless_turned_off() {
+        assert!(parse("").unwrap().kick_on_reload);
+        assert!(!parse("kick_on_reload = false").unwrap().kick_on_reload);
     }
 }
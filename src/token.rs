@@ -0,0 +1,24 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Number of random bytes in an issued token.
+const TOKEN_BYTES: usize = 20;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Creates a new random account token. Only the server ever issues these,
+/// the client just stores and echoes it back on the next login.
+pub fn generate() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    to_hex(&bytes)
+}
+
+/// Hashes a token for storage. The database never sees the raw token, so a
+/// leaked dump cannot be used to log in as someone else.
+pub fn hash(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
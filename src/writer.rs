@@ -1,3 +1,6 @@
+use crate::data_ref::DataRef;
+use crate::logic_long::LogicLong;
+
 pub struct ByteWriter {
     pub buffer: Vec<u8>,
     pub offset: usize,
@@ -134,6 +137,18 @@ impl ByteWriter {
         }
     }
 
+    pub fn write_data_ref(&mut self, data: DataRef) {
+        self.write_sc_id(data.class_id, data.instance_id);
+    }
+
+    pub fn write_logic_long(&mut self, id: LogicLong) {
+        self.write_long(id.high_id as i32, id.low_id as i32);
+    }
+
+    pub fn write_vlogic_long(&mut self, id: LogicLong) {
+        self.write_vlong(id.high_id as i32, id.low_id as i32);
+    }
+
     pub fn write_vlong(&mut self, high: i32, low: i32) {
         self.bitoffset = 0;
 